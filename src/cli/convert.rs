@@ -0,0 +1,92 @@
+use super::verify_file;
+use crate::{get_content, process_convert, CmdExecutor};
+use clap::Parser;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy)]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+    Json5,
+    Dotenv,
+}
+
+#[derive(Debug, Parser)]
+pub struct ConvertOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(
+        long,
+        value_parser = parse_config_format,
+        help = "json, yaml, toml, json5 or env (default: detected from the file name)"
+    )]
+    pub from: Option<ConfigFormat>,
+
+    #[arg(long, value_parser = parse_config_format, help = "json, yaml, toml, json5 or env")]
+    pub to: ConfigFormat,
+
+    #[arg(short, long, help = "Output file (default: stdout)")]
+    pub output: Option<String>,
+}
+
+impl CmdExecutor for ConvertOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let from = match self.from {
+            Some(format) => format,
+            None => detect_format(&self.input).ok_or_else(|| {
+                anyhow::anyhow!("cannot tell the format of {}, pass --from", self.input)
+            })?,
+        };
+        let content = String::from_utf8(get_content(&self.input)?)?;
+        let ret = process_convert(&content, from, self.to)?;
+        match self.output {
+            Some(output) => std::fs::write(output, ret)?,
+            None => print!("{}", ret),
+        }
+        Ok(())
+    }
+}
+
+// .env, .env.local and prod.env are all dotenv files
+fn detect_format(input: &str) -> Option<ConfigFormat> {
+    let path = Path::new(input);
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name == ".env" || name.starts_with(".env.") {
+        return Some(ConfigFormat::Dotenv);
+    }
+    path.extension()?.to_string_lossy().parse().ok()
+}
+
+fn parse_config_format(format: &str) -> Result<ConfigFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for ConfigFormat {
+    type Err = anyhow::Error;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "json" => Ok(ConfigFormat::Json),
+            "yaml" | "yml" => Ok(ConfigFormat::Yaml),
+            "toml" => Ok(ConfigFormat::Toml),
+            "json5" => Ok(ConfigFormat::Json5),
+            "env" | "dotenv" => Ok(ConfigFormat::Dotenv),
+            v => anyhow::bail!("Unsupported config format {}", v),
+        }
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFormat::Json => write!(f, "json"),
+            ConfigFormat::Yaml => write!(f, "yaml"),
+            ConfigFormat::Toml => write!(f, "toml"),
+            ConfigFormat::Json5 => write!(f, "json5"),
+            ConfigFormat::Dotenv => write!(f, "env"),
+        }
+    }
+}
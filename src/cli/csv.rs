@@ -1,20 +1,55 @@
 use super::verify_file;
-use crate::{process_csv, CmdExecutor};
+use crate::{
+    delimiter_byte, get_content, is_spreadsheet, process_csv, process_csv_chart, process_csv_count,
+    process_csv_fake, process_csv_index, CmdExecutor, CsvTableReader, Deduplicator, FakeSchema,
+    Filler, FixedWidthReader, FixedWidthSpec, FixedWidthWriter, GeoJsonWriter, GeometrySource,
+    Masker, Melt, Pivot, RecordTransform, SampleSize, Sampler, SpreadsheetReader, TableReader,
+    TableWriter, TransformRule, Transformer, ValueWriter,
+};
 use clap::Parser;
+use enum_dispatch::enum_dispatch;
 use std::fmt;
 use std::fmt::Formatter;
+use std::fs::File;
+use std::io::{self, BufWriter};
 use std::str::FromStr;
 
 #[derive(Debug, Clone, Copy)]
 pub enum OutputFormat {
     Json,
     Yaml,
+    FixedWidth,
+    GeoJson,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum InputFormat {
+    Csv,
+    Spreadsheet,
+    FixedWidth,
 }
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CsvOpts {
-    #[arg(short, long, value_parser = verify_file)]
-    pub input: String,
+    #[command(subcommand)]
+    pub cmd: Option<CsvSubCommand>,
+
+    #[arg(short, long, value_parser = verify_file, required = true)]
+    pub input: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = parse_input_format,
+        help = "csv, spreadsheet or fixed-width (default: detected from the file extension)"
+    )]
+    pub input_format: Option<InputFormat>,
+
+    #[arg(long, value_parser = verify_file, help = "YAML column spec for fixed-width input/output")]
+    pub width_spec: Option<String>,
+
+    #[command(flatten)]
+    pub geo: GeoJsonOpts,
 
     #[arg(long, value_parser = parse_format, default_value="json")]
     pub format: OutputFormat,
@@ -27,29 +62,562 @@ pub struct CsvOpts {
 
     #[arg(long, default_value_t = true)]
     pub header: bool,
+
+    #[arg(long, help = "Sheet name or 0-based index for .xlsx/.xls/.ods input")]
+    pub sheet: Option<String>,
+
+    #[arg(long, help = "Cell range for spreadsheet input, e.g. A1:D20")]
+    pub range: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = parse_row_slice,
+        help = "Only read CSV records start..end (0-based, end exclusive), seeking via the index when present"
+    )]
+    pub slice: Option<RowSlice>,
+
+    #[arg(
+        long,
+        default_value_t = 1,
+        help = "Worker threads for parsing and converting, 0 uses every core"
+    )]
+    pub threads: usize,
+
+    #[arg(long, help = "Randomly sample N rows")]
+    pub sample: Option<usize>,
+
+    #[arg(long, value_parser = parse_fraction, conflicts_with = "sample")]
+    pub sample_fraction: Option<f64>,
+
+    #[arg(long, help = "Seed the sampler for reproducible output")]
+    pub seed: Option<u64>,
+
+    #[arg(
+        long,
+        num_args = 0..,
+        value_delimiter = ',',
+        help = "Drop rows whose given columns (default: all) were already seen"
+    )]
+    pub dedup: Option<Vec<String>>,
+
+    #[arg(long, value_parser = parse_dedup_keep, default_value = "first")]
+    pub keep: DedupKeep,
+
+    #[arg(
+        long,
+        value_parser = parse_mask_rule,
+        help = "Mask a column, e.g. Name=redact, Phone=partial:4, Email=pseudonym, DOB=date:year, Age=number:10"
+    )]
+    pub mask: Vec<MaskRule>,
+
+    #[arg(long, value_parser = verify_file, help = "Key file for pseudonym masking")]
+    pub mask_key: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = parse_fill_rule,
+        help = "Fill missing cells of a column with a constant, e.g. Nationality=unknown"
+    )]
+    pub fill: Vec<FillRule>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Carry the last non-missing value of these columns forward"
+    )]
+    pub fill_down: Vec<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Fill these numeric columns with their mean"
+    )]
+    pub fill_mean: Vec<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Fill these numeric columns with their median"
+    )]
+    pub fill_median: Vec<String>,
+
+    #[arg(
+        long,
+        num_args = 1..,
+        value_delimiter = ',',
+        help = "Values that count as missing, e.g. ,NA,null,- (default: empty cells); they are written as null in json/yaml"
+    )]
+    pub missing: Option<Vec<String>>,
+
+    #[arg(
+        long,
+        value_parser = parse_transform_rule,
+        help = "Rewrite a column with a pipeline, e.g. Name='trim | upper' or DOB=\"date('%d/%m/%Y')\""
+    )]
+    pub transform: Vec<TransformRule>,
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum CsvSubCommand {
+    #[command(about = "Reshape long rows into a wide table")]
+    Pivot(CsvPivotOpts),
+
+    #[command(about = "Reshape wide columns into long rows, the inverse of pivot")]
+    Melt(CsvMeltOpts),
+
+    #[command(about = "Write a sidecar index of record offsets for fast slicing and counting")]
+    Index(CsvIndexOpts),
+
+    #[command(about = "Count the records in a csv, using its index when present")]
+    Count(CsvCountOpts),
+
+    #[command(about = "Draw a histogram, value-count bar chart or sparkline of a column")]
+    Chart(CsvChartOpts),
+
+    #[command(about = "Generate fake csv data from a YAML schema")]
+    Fake(CsvFakeOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvPivotOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+
+    #[arg(short, long, default_value_t = ',')]
+    pub delimiter: char,
+
+    #[arg(long, value_parser = parse_format, default_value = "json")]
+    pub format: OutputFormat,
+
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[arg(long, value_parser = verify_file)]
+    pub width_spec: Option<String>,
+
+    #[command(flatten)]
+    pub geo: GeoJsonOpts,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        required = true,
+        help = "Columns that identify a row"
+    )]
+    pub index: Vec<String>,
+
+    #[arg(long, help = "Column whose values become the new columns")]
+    pub columns: String,
+
+    #[arg(long, help = "Column to aggregate into the cells")]
+    pub values: String,
+
+    #[arg(long, value_parser = parse_aggregation, default_value = "sum")]
+    pub agg: Aggregation,
+}
+
+impl CmdExecutor for CsvPivotOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let reader = table_reader(&self.input, self.delimiter)?;
+        let writer = table_writer(self.format, self.width_spec.as_deref(), &self.geo, None, 1)?;
+        let pivot = Pivot::new(self.index, self.columns, self.values, self.agg);
+        let output = output_path(self.output, self.format);
+        process_csv(reader, output, writer, vec![Box::new(pivot)])
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvMeltOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+
+    #[arg(short, long, default_value_t = ',')]
+    pub delimiter: char,
+
+    #[arg(long, value_parser = parse_format, default_value = "json")]
+    pub format: OutputFormat,
+
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    #[arg(long, value_parser = verify_file)]
+    pub width_spec: Option<String>,
+
+    #[command(flatten)]
+    pub geo: GeoJsonOpts,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        required = true,
+        help = "Columns kept on every row"
+    )]
+    pub id: Vec<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Columns to unpivot (default: all non-id columns)"
+    )]
+    pub value_vars: Vec<String>,
+
+    #[arg(long, default_value = "variable")]
+    pub var_name: String,
+
+    #[arg(long, default_value = "value")]
+    pub value_name: String,
+}
+
+impl CmdExecutor for CsvMeltOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let reader = table_reader(&self.input, self.delimiter)?;
+        let writer = table_writer(self.format, self.width_spec.as_deref(), &self.geo, None, 1)?;
+        let melt = Melt::new(self.id, self.value_vars, self.var_name, self.value_name);
+        let output = output_path(self.output, self.format);
+        process_csv(reader, output, writer, vec![Box::new(melt)])
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvIndexOpts {
+    #[arg(value_parser = verify_file)]
+    pub input: String,
+
+    #[arg(short, long, default_value_t = ',')]
+    pub delimiter: char,
+}
+
+impl CmdExecutor for CsvIndexOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let (path, count) = process_csv_index(&self.input, delimiter_byte(self.delimiter)?)?;
+        println!("Indexed {} records into {}", count, path.display());
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvCountOpts {
+    #[arg(value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(short, long, default_value_t = ',')]
+    pub delimiter: char,
+}
+
+impl CmdExecutor for CsvCountOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let count = process_csv_count(&self.input, delimiter_byte(self.delimiter)?)?;
+        println!("{}", count);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvChartOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(short, long, default_value_t = ',')]
+    pub delimiter: char,
+
+    #[arg(short, long, help = "Column to chart")]
+    pub column: String,
+
+    #[arg(long, value_parser = parse_chart_kind, default_value = "histogram")]
+    pub kind: ChartKind,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u16).range(1..=1000),
+        default_value = "10",
+        help = "Histogram bins (1-1000)"
+    )]
+    pub bins: u16,
+
+    #[arg(long, help = "Draw one chart per value of this column")]
+    pub group_by: Option<String>,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u16).range(1..=1000),
+        default_value = "40",
+        help = "Width of the longest bar or of the sparkline, in characters (1-1000)"
+    )]
+    pub width: u16,
+}
+
+impl CmdExecutor for CsvChartOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let reader = table_reader(&self.input, self.delimiter)?;
+        let chart = process_csv_chart(
+            reader,
+            &self.column,
+            self.group_by.as_deref(),
+            self.kind,
+            self.bins as usize,
+            self.width as usize,
+        )?;
+        print!("{}", chart);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvFakeOpts {
+    #[arg(long, value_parser = verify_file, help = "YAML schema declaring the columns to generate")]
+    pub schema: String,
+
+    #[arg(long, default_value_t = 100)]
+    pub rows: usize,
+
+    #[arg(long, help = "Seed the generator for reproducible output")]
+    pub seed: Option<u64>,
+
+    #[arg(short, long, help = "Output csv file (default: stdout)")]
+    pub output: Option<String>,
+}
+
+impl CmdExecutor for CsvFakeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let schema = FakeSchema::load(&self.schema)?;
+        match self.output {
+            Some(output) => {
+                let file = BufWriter::new(File::create(output)?);
+                process_csv_fake(&schema, self.rows, self.seed, file)
+            }
+            None => process_csv_fake(&schema, self.rows, self.seed, io::stdout().lock()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ChartKind {
+    Histogram,
+    Bar,
+    Sparkline,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct GeoJsonOpts {
+    #[arg(long, requires = "lon", help = "Latitude column for geojson output")]
+    pub lat: Option<String>,
+
+    #[arg(long, requires = "lat", help = "Longitude column for geojson output")]
+    pub lon: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["lat", "lon"],
+        help = "WKT geometry column for geojson output"
+    )]
+    pub wkt: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RowSlice {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Aggregation {
+    Sum,
+    Mean,
+    Count,
+    Min,
+    Max,
+    First,
+    Last,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DedupKeep {
+    First,
+    Last,
+}
+
+#[derive(Debug, Clone)]
+pub struct MaskRule {
+    pub column: String,
+    pub strategy: MaskStrategy,
+}
+
+#[derive(Debug, Clone)]
+pub struct FillRule {
+    pub column: String,
+    pub strategy: FillStrategy,
+}
+
+#[derive(Debug, Clone)]
+pub enum FillStrategy {
+    Value(String),
+    Down,
+    Mean,
+    Median,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum MaskStrategy {
+    Redact,
+    Partial(usize),
+    Pseudonym(usize),
+    Hash(usize),
+    Year,
+    Month,
+    Number(u64),
 }
 
 impl CmdExecutor for CsvOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let output = if let Some(output) = self.output {
-            output
+        let input = match (self.cmd, self.input) {
+            (Some(cmd), _) => return cmd.execute().await,
+            (None, Some(input)) => input,
+            (None, None) => anyhow::bail!("--input is required"),
+        };
+        let output = output_path(self.output, self.format);
+        let mut transforms: Vec<Box<dyn RecordTransform>> = Vec::new();
+        // computed fills come first, so constants act as the fallback for what they leave empty
+        let fills = [
+            (self.fill_down, FillStrategy::Down),
+            (self.fill_mean, FillStrategy::Mean),
+            (self.fill_median, FillStrategy::Median),
+        ]
+        .into_iter()
+        .flat_map(|(columns, strategy)| {
+            columns.into_iter().map(move |column| FillRule {
+                column,
+                strategy: strategy.clone(),
+            })
+        })
+        .chain(self.fill)
+        .collect::<Vec<_>>();
+        if !fills.is_empty() {
+            let missing = self.missing.clone().unwrap_or_else(|| vec![String::new()]);
+            transforms.push(Box::new(Filler::new(fills, missing)));
+        }
+        if !self.transform.is_empty() {
+            transforms.push(Box::new(Transformer::new(self.transform)));
+        }
+        if let Some(columns) = self.dedup {
+            transforms.push(Box::new(Deduplicator::new(columns, self.keep)));
+        }
+        let sample = match (self.sample, self.sample_fraction) {
+            (Some(n), _) => Some(SampleSize::Count(n)),
+            (None, Some(fraction)) => Some(SampleSize::Fraction(fraction)),
+            (None, None) => None,
+        };
+        if let Some(size) = sample {
+            transforms.push(Box::new(Sampler::new(size, self.seed)));
+        }
+        if !self.mask.is_empty() {
+            let key = self.mask_key.as_deref().map(get_content).transpose()?;
+            transforms.push(Box::new(Masker::try_new(self.mask, key.as_deref())?));
+        }
+        let input_format = self.input_format.unwrap_or(if is_spreadsheet(&input) {
+            InputFormat::Spreadsheet
         } else {
-            format!("output.{}", self.format)
+            InputFormat::Csv
+        });
+        if self.slice.is_some() && !matches!(input_format, InputFormat::Csv) {
+            anyhow::bail!(
+                "--slice only applies to csv input, not {} input",
+                input_format
+            );
+        }
+        let reader: Box<dyn TableReader> = match input_format {
+            InputFormat::Csv => {
+                let reader = CsvTableReader::try_new(&input, self.delimiter)?;
+                Box::new(reader.with_slice(self.slice).with_threads(self.threads))
+            }
+            InputFormat::Spreadsheet => {
+                Box::new(SpreadsheetReader::new(&input, self.sheet, self.range))
+            }
+            InputFormat::FixedWidth => {
+                let spec = self.width_spec.as_deref().ok_or_else(require_width_spec)?;
+                Box::new(FixedWidthReader::new(&input, FixedWidthSpec::load(spec)?))
+            }
         };
-        process_csv(&self.input, output, self.format)?;
+        let writer = table_writer(
+            self.format,
+            self.width_spec.as_deref(),
+            &self.geo,
+            self.missing,
+            self.threads,
+        )?;
+        process_csv(reader, output, writer, transforms)?;
         Ok(())
     }
 }
 
+fn table_reader(input: &str, delimiter: char) -> anyhow::Result<Box<dyn TableReader>> {
+    if is_spreadsheet(input) {
+        Ok(Box::new(SpreadsheetReader::new(input, None, None)))
+    } else {
+        Ok(Box::new(CsvTableReader::try_new(input, delimiter)?))
+    }
+}
+
+fn table_writer(
+    format: OutputFormat,
+    width_spec: Option<&str>,
+    geo: &GeoJsonOpts,
+    missing: Option<Vec<String>>,
+    threads: usize,
+) -> anyhow::Result<Box<dyn TableWriter>> {
+    match format {
+        OutputFormat::FixedWidth => {
+            let spec = width_spec.ok_or_else(require_width_spec)?;
+            Ok(Box::new(FixedWidthWriter::new(FixedWidthSpec::load(spec)?)))
+        }
+        OutputFormat::GeoJson => {
+            let source = match geo.clone() {
+                GeoJsonOpts {
+                    lat: Some(lat),
+                    lon: Some(lon),
+                    ..
+                } => GeometrySource::LatLon { lat, lon },
+                GeoJsonOpts { wkt: Some(wkt), .. } => GeometrySource::Wkt(wkt),
+                _ => anyhow::bail!("geojson output requires --lat and --lon, or --wkt"),
+            };
+            Ok(Box::new(GeoJsonWriter::new(source)))
+        }
+        format => Ok(Box::new(
+            ValueWriter::new(format)
+                .with_threads(threads)
+                .with_missing(missing),
+        )),
+    }
+}
+
+fn output_path(output: Option<String>, format: OutputFormat) -> String {
+    output.unwrap_or_else(|| format!("output.{}", format.extension()))
+}
+
+fn require_width_spec() -> anyhow::Error {
+    anyhow::anyhow!("fixed-width input/output requires --width-spec")
+}
+
 fn parse_format(format: &str) -> Result<OutputFormat, anyhow::Error> {
     format.parse()
 }
 
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::FixedWidth => "txt",
+            format => (*format).into(),
+        }
+    }
+}
+
 impl From<OutputFormat> for &'static str {
     fn from(format: OutputFormat) -> Self {
         match format {
             OutputFormat::Json => "json",
             OutputFormat::Yaml => "yaml",
+            OutputFormat::FixedWidth => "fixed-width",
+            OutputFormat::GeoJson => "geojson",
         }
     }
 }
@@ -60,6 +628,8 @@ impl FromStr for OutputFormat {
         match format.to_lowercase().as_str() {
             "json" => Ok(OutputFormat::Json),
             "yaml" => Ok(OutputFormat::Yaml),
+            "fixed-width" => Ok(OutputFormat::FixedWidth),
+            "geojson" => Ok(OutputFormat::GeoJson),
             v => anyhow::bail!("Unsupported format {}", v),
         }
     }
@@ -70,3 +640,242 @@ impl fmt::Display for OutputFormat {
         write!(f, "{}", Into::<&str>::into(*self))
     }
 }
+
+fn parse_fraction(fraction: &str) -> Result<f64, anyhow::Error> {
+    let fraction: f64 = fraction.parse()?;
+    if (0.0..=1.0).contains(&fraction) {
+        Ok(fraction)
+    } else {
+        anyhow::bail!("sample fraction must be between 0 and 1")
+    }
+}
+
+fn parse_dedup_keep(keep: &str) -> Result<DedupKeep, anyhow::Error> {
+    keep.parse()
+}
+
+impl FromStr for DedupKeep {
+    type Err = anyhow::Error;
+    fn from_str(keep: &str) -> Result<Self, Self::Err> {
+        match keep {
+            "first" => Ok(DedupKeep::First),
+            "last" => Ok(DedupKeep::Last),
+            v => anyhow::bail!("Unsupported dedup keep {}", v),
+        }
+    }
+}
+
+impl fmt::Display for DedupKeep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DedupKeep::First => write!(f, "first"),
+            DedupKeep::Last => write!(f, "last"),
+        }
+    }
+}
+
+fn parse_fill_rule(rule: &str) -> Result<FillRule, anyhow::Error> {
+    let (column, value) = rule
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("fill rule must be col=value"))?;
+    Ok(FillRule {
+        column: column.to_string(),
+        strategy: FillStrategy::Value(value.to_string()),
+    })
+}
+
+impl fmt::Display for FillStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FillStrategy::Value(value) => write!(f, "value {:?}", value),
+            FillStrategy::Down => write!(f, "previous value"),
+            FillStrategy::Mean => write!(f, "mean"),
+            FillStrategy::Median => write!(f, "median"),
+        }
+    }
+}
+
+fn parse_transform_rule(rule: &str) -> Result<TransformRule, anyhow::Error> {
+    rule.parse()
+}
+
+fn parse_mask_rule(rule: &str) -> Result<MaskRule, anyhow::Error> {
+    let (column, strategy) = rule
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("mask rule must be col=strategy"))?;
+    Ok(MaskRule {
+        column: column.to_string(),
+        strategy: strategy.parse()?,
+    })
+}
+
+impl FromStr for MaskStrategy {
+    type Err = anyhow::Error;
+    fn from_str(strategy: &str) -> Result<Self, Self::Err> {
+        let (name, arg) = match strategy.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (strategy, None),
+        };
+        let hex_len = |arg: Option<&str>| -> Result<usize, anyhow::Error> {
+            let len = arg.map(str::parse).transpose()?.unwrap_or(16);
+            if !(1..=64).contains(&len) {
+                anyhow::bail!("hash length must be between 1 and 64");
+            }
+            Ok(len)
+        };
+        match (name, arg) {
+            ("redact", None) => Ok(MaskStrategy::Redact),
+            ("partial", arg) => Ok(MaskStrategy::Partial(
+                arg.map(str::parse).transpose()?.unwrap_or(4),
+            )),
+            ("pseudonym", arg) => Ok(MaskStrategy::Pseudonym(hex_len(arg)?)),
+            ("hash", arg) => Ok(MaskStrategy::Hash(hex_len(arg)?)),
+            ("date", Some("year")) => Ok(MaskStrategy::Year),
+            ("date", Some("month")) => Ok(MaskStrategy::Month),
+            ("number", Some(width)) => match width.parse()? {
+                0 => anyhow::bail!("number width must be positive"),
+                width => Ok(MaskStrategy::Number(width)),
+            },
+            _ => anyhow::bail!("Unsupported mask strategy {}", strategy),
+        }
+    }
+}
+
+impl fmt::Display for MaskStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MaskStrategy::Redact => write!(f, "redact"),
+            MaskStrategy::Partial(keep) => write!(f, "partial:{}", keep),
+            MaskStrategy::Pseudonym(len) => write!(f, "pseudonym:{}", len),
+            MaskStrategy::Hash(len) => write!(f, "hash:{}", len),
+            MaskStrategy::Year => write!(f, "date:year"),
+            MaskStrategy::Month => write!(f, "date:month"),
+            MaskStrategy::Number(width) => write!(f, "number:{}", width),
+        }
+    }
+}
+
+fn parse_input_format(format: &str) -> Result<InputFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for InputFormat {
+    type Err = anyhow::Error;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(InputFormat::Csv),
+            "spreadsheet" => Ok(InputFormat::Spreadsheet),
+            "fixed-width" => Ok(InputFormat::FixedWidth),
+            v => anyhow::bail!("Unsupported input format {}", v),
+        }
+    }
+}
+
+impl fmt::Display for InputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            InputFormat::Csv => write!(f, "csv"),
+            InputFormat::Spreadsheet => write!(f, "spreadsheet"),
+            InputFormat::FixedWidth => write!(f, "fixed-width"),
+        }
+    }
+}
+
+fn parse_chart_kind(kind: &str) -> Result<ChartKind, anyhow::Error> {
+    kind.parse()
+}
+
+impl FromStr for ChartKind {
+    type Err = anyhow::Error;
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind.to_lowercase().as_str() {
+            "histogram" => Ok(ChartKind::Histogram),
+            "bar" => Ok(ChartKind::Bar),
+            "sparkline" => Ok(ChartKind::Sparkline),
+            v => anyhow::bail!("Unsupported chart kind {}", v),
+        }
+    }
+}
+
+impl fmt::Display for ChartKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ChartKind::Histogram => write!(f, "histogram"),
+            ChartKind::Bar => write!(f, "bar"),
+            ChartKind::Sparkline => write!(f, "sparkline"),
+        }
+    }
+}
+
+fn parse_aggregation(agg: &str) -> Result<Aggregation, anyhow::Error> {
+    agg.parse()
+}
+
+impl FromStr for Aggregation {
+    type Err = anyhow::Error;
+    fn from_str(agg: &str) -> Result<Self, Self::Err> {
+        match agg.to_lowercase().as_str() {
+            "sum" => Ok(Aggregation::Sum),
+            "mean" => Ok(Aggregation::Mean),
+            "count" => Ok(Aggregation::Count),
+            "min" => Ok(Aggregation::Min),
+            "max" => Ok(Aggregation::Max),
+            "first" => Ok(Aggregation::First),
+            "last" => Ok(Aggregation::Last),
+            v => anyhow::bail!("Unsupported aggregation {}", v),
+        }
+    }
+}
+
+impl fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let agg = match self {
+            Aggregation::Sum => "sum",
+            Aggregation::Mean => "mean",
+            Aggregation::Count => "count",
+            Aggregation::Min => "min",
+            Aggregation::Max => "max",
+            Aggregation::First => "first",
+            Aggregation::Last => "last",
+        };
+        write!(f, "{}", agg)
+    }
+}
+
+impl RowSlice {
+    pub fn new(start: usize, end: Option<usize>) -> Self {
+        Self { start, end }
+    }
+}
+
+fn parse_row_slice(slice: &str) -> Result<RowSlice, anyhow::Error> {
+    slice.parse()
+}
+
+impl FromStr for RowSlice {
+    type Err = anyhow::Error;
+    fn from_str(slice: &str) -> Result<Self, Self::Err> {
+        let (start, end) = slice
+            .split_once("..")
+            .ok_or_else(|| anyhow::anyhow!("slice must look like start..end"))?;
+        let start = if start.is_empty() { 0 } else { start.parse()? };
+        let end = if end.is_empty() {
+            None
+        } else {
+            Some(end.parse()?)
+        };
+        if end.is_some_and(|end| end < start) {
+            anyhow::bail!("slice end must not be before its start");
+        }
+        Ok(RowSlice::new(start, end))
+    }
+}
+
+impl fmt::Display for RowSlice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.end {
+            Some(end) => write!(f, "{}..{}", self.start, end),
+            None => write!(f, "{}..", self.start),
+        }
+    }
+}
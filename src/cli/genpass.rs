@@ -1,10 +1,229 @@
-use crate::{process_genpass, CmdExecutor};
+use super::verify_file;
+use crate::{
+    derive_password, format_passwords, generate_strong, generate_unbreached, get_content,
+    master_key, BreachList, CharClass, CmdExecutor, GeneratedPassword, PassphraseSpec,
+    PasswordPattern, PasswordPolicy, PasswordSource, PasswordStrength, StrengthTarget, Wordlist,
+    MAX_PASSWORD_LENGTH,
+};
 use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy)]
+pub enum WordCase {
+    Lower,
+    Title,
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PasswordFormat {
+    Plain,
+    Csv,
+    Json,
+}
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct GenPassOpts {
-    #[arg(short, long, default_value_t = 16)]
-    pub length: u8,
+    #[command(subcommand)]
+    pub cmd: Option<GenPassSubCommand>,
+
+    #[command(flatten)]
+    pub policy: PasswordPolicyOpts,
+
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u8).range(0..=4),
+        help = "Lengthen the password until its zxcvbn score reaches this (0-4)"
+    )]
+    pub min_score: Option<u8>,
+
+    #[arg(
+        long,
+        help = "Lengthen the password until its entropy reaches this many bits"
+    )]
+    pub min_entropy_bits: Option<f64>,
+
+    #[arg(
+        short = 'n',
+        long,
+        default_value_t = 1,
+        help = "Number of passwords to generate"
+    )]
+    pub count: usize,
+
+    #[arg(
+        long,
+        value_parser = parse_password_format,
+        default_value = "plain",
+        help = "plain, csv or json"
+    )]
+    pub format: PasswordFormat,
+
+    #[arg(
+        long,
+        help = "Add the zxcvbn score, entropy and crack time to each password"
+    )]
+    pub strength: bool,
+
+    #[arg(
+        long,
+        value_parser = verify_file,
+        help = "Draw again any password found in this Have I Been Pwned file"
+    )]
+    pub hibp: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "charset",
+        requires = "wordlist",
+        help = "Generate a passphrase of this many words from --wordlist instead"
+    )]
+    pub words: Option<usize>,
+
+    #[arg(
+        long,
+        value_parser = parse_pattern,
+        conflicts_with_all = [
+            "words",
+            "length",
+            "min_upper",
+            "min_lower",
+            "min_number",
+            "min_symbol",
+            "max_upper",
+            "max_lower",
+            "max_number",
+            "max_symbol",
+            "no_repeat",
+        ],
+        help = "Fixed format such as 'Cvcv-9999-!!': C/c consonant, V/v vowel, 9 digit, ! symbol, * any, {n,m} repeats, \\ escapes; --charset narrows them all"
+    )]
+    pub pattern: Option<PasswordPattern>,
+
+    #[arg(
+        long,
+        value_parser = verify_file,
+        requires = "words",
+        help = "Word per line, or diceware lines such as the EFF large wordlist (eff.org/dice, CC BY 3.0 US); words holding the separator are skipped"
+    )]
+    pub wordlist: Option<String>,
+
+    #[arg(long, default_value = "-", requires = "words")]
+    pub separator: String,
+
+    #[arg(
+        long,
+        value_parser = parse_word_case,
+        default_value = "lower",
+        requires = "words",
+        help = "lower, title or upper"
+    )]
+    pub case: WordCase,
+
+    #[arg(
+        long,
+        default_value_t = 0,
+        requires = "words",
+        help = "Insert a group of this many random digits"
+    )]
+    pub add_digits: usize,
+
+    #[arg(
+        long,
+        default_value_t = 0,
+        requires = "words",
+        help = "Insert a group of this many random symbols"
+    )]
+    pub add_symbols: usize,
+}
+
+impl CmdExecutor for GenPassOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        if let Some(cmd) = self.cmd {
+            return cmd.execute().await;
+        }
+        let policy = self.policy.policy();
+        let mut rng = rand::thread_rng();
+        // character passwords only report their strength, as they always have
+        let show_entropy = self.words.is_some() || self.pattern.is_some();
+        let mut source = match (self.words, self.pattern) {
+            (Some(words), _) => {
+                let path = self.wordlist.as_deref().expect("clap requires --wordlist");
+                let wordlist = Wordlist::load(path, &self.separator)?;
+                let spec = PassphraseSpec {
+                    words,
+                    separator: self.separator,
+                    case: self.case,
+                    digits: self.add_digits,
+                    symbols: self.add_symbols,
+                    digit_chars: policy.class_chars(CharClass::Number),
+                    symbol_chars: policy.class_chars(CharClass::Symbol),
+                };
+                PasswordSource::Passphrase(wordlist, spec)
+            }
+            (None, Some(pattern)) => PasswordSource::Pattern(pattern, policy),
+            (None, None) => PasswordSource::Chars(policy),
+        };
+        let target = StrengthTarget {
+            min_score: self.min_score,
+            min_entropy_bits: self.min_entropy_bits,
+        };
+        let breaches = match &self.hibp {
+            Some(path) => Some(BreachList::open(path, None)?),
+            None => None,
+        };
+        let mut generate = |source: &mut PasswordSource| match &breaches {
+            Some(list) => generate_unbreached(&mut rng, source, &target, list),
+            None => generate_strong(&mut rng, source, &target),
+        };
+
+        // a single password keeps the interactive output, with its strength on stderr
+        if self.count == 1 && !self.strength && matches!(self.format, PasswordFormat::Plain) {
+            let password = generate(&mut source)?;
+            println!("{}", password);
+            if show_entropy {
+                eprintln!("Entropy: {:.2} bits", source.entropy()?);
+            }
+            let estimate = zxcvbn::zxcvbn(&password, &[])?;
+            eprintln!("Password strength: {}", estimate.score());
+            return Ok(());
+        }
+        let passwords = (0..self.count)
+            .map(|_| {
+                let password = generate(&mut source)?;
+                let strength = if self.strength {
+                    Some(PasswordStrength::estimate(&password, source.entropy()?)?)
+                } else {
+                    None
+                };
+                Ok(GeneratedPassword { password, strength })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        print!("{}", format_passwords(&passwords, self.format)?);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum GenPassSubCommand {
+    #[command(about = "Derive a reproducible password for a site from a master key")]
+    Derive(GenPassDeriveOpts),
+}
+
+// The character and class rules, shared by random and derived passwords
+#[derive(Debug, Clone, Parser)]
+pub struct PasswordPolicyOpts {
+    #[arg(
+        short,
+        long,
+        default_value_t = 16,
+        value_parser = clap::value_parser!(u16).range(1..=MAX_PASSWORD_LENGTH as i64)
+    )]
+    pub length: u16,
 
     #[arg(long)]
     pub no_upper_case: bool,
@@ -17,16 +236,93 @@ pub struct GenPassOpts {
 
     #[arg(long)]
     pub no_symbol: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "no_upper_case",
+            "no_lower_case",
+            "no_number",
+            "no_symbol",
+            "symbols",
+            "allow_ambiguous",
+        ],
+        help = "Draw every character from this alphabet instead"
+    )]
+    pub charset: Option<String>,
+
+    #[arg(long, help = "Symbols to draw from (default: !@#$%^&*_)")]
+    pub symbols: Option<String>,
+
+    #[arg(long, default_value = "", help = "Characters never to use")]
+    pub exclude: String,
+
+    #[arg(long, help = "Also use the look-alike characters 0, O, I and l")]
+    pub allow_ambiguous: bool,
+
+    #[arg(long)]
+    pub min_upper: Option<usize>,
+
+    #[arg(long)]
+    pub min_lower: Option<usize>,
+
+    #[arg(long)]
+    pub min_number: Option<usize>,
+
+    #[arg(long)]
+    pub min_symbol: Option<usize>,
+
+    #[arg(long)]
+    pub max_upper: Option<usize>,
+
+    #[arg(long)]
+    pub max_lower: Option<usize>,
+
+    #[arg(long)]
+    pub max_number: Option<usize>,
+
+    #[arg(long)]
+    pub max_symbol: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Reject repeated (aa) and sequential (abc, 321) characters"
+    )]
+    pub no_repeat: bool,
 }
 
-impl CmdExecutor for GenPassOpts {
+#[derive(Debug, Parser)]
+pub struct GenPassDeriveOpts {
+    #[arg(
+        short,
+        long,
+        value_parser = verify_file,
+        help = "Master key file, trailing whitespace ignored when it is text; keep it secret, as it yields every derived password"
+    )]
+    pub key: String,
+
+    #[arg(long)]
+    pub site: String,
+
+    #[arg(long)]
+    pub login: String,
+
+    #[arg(long, default_value_t = 1, help = "Bump to rotate the password")]
+    pub counter: u64,
+
+    #[command(flatten)]
+    pub policy: PasswordPolicyOpts,
+}
+
+impl CmdExecutor for GenPassDeriveOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let password = process_genpass(
-            self.length,
-            !self.no_upper_case,
-            !self.no_lower_case,
-            !self.no_number,
-            !self.no_symbol,
+        let key = get_content(&self.key)?;
+        let password = derive_password(
+            master_key(&key),
+            &self.site,
+            &self.login,
+            self.counter,
+            &self.policy.policy(),
         )?;
         println!("{}", password);
         let estimate = zxcvbn::zxcvbn(&password, &[])?;
@@ -34,3 +330,84 @@ impl CmdExecutor for GenPassOpts {
         Ok(())
     }
 }
+
+impl PasswordPolicyOpts {
+    fn policy(&self) -> PasswordPolicy {
+        PasswordPolicy {
+            length: self.length as usize,
+            upper: !self.no_upper_case,
+            lower: !self.no_lower_case,
+            number: !self.no_number,
+            symbol: !self.no_symbol,
+            charset: self.charset.clone(),
+            symbols: self.symbols.clone(),
+            exclude: self.exclude.clone(),
+            allow_ambiguous: self.allow_ambiguous,
+            min_upper: self.min_upper,
+            min_lower: self.min_lower,
+            min_number: self.min_number,
+            min_symbol: self.min_symbol,
+            max_upper: self.max_upper,
+            max_lower: self.max_lower,
+            max_number: self.max_number,
+            max_symbol: self.max_symbol,
+            no_repeat: self.no_repeat,
+        }
+    }
+}
+
+fn parse_word_case(case: &str) -> Result<WordCase, anyhow::Error> {
+    case.parse()
+}
+
+impl FromStr for WordCase {
+    type Err = anyhow::Error;
+    fn from_str(case: &str) -> Result<Self, Self::Err> {
+        match case.to_lowercase().as_str() {
+            "lower" => Ok(WordCase::Lower),
+            "title" => Ok(WordCase::Title),
+            "upper" => Ok(WordCase::Upper),
+            v => anyhow::bail!("Unsupported word case {}", v),
+        }
+    }
+}
+
+impl fmt::Display for WordCase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WordCase::Lower => write!(f, "lower"),
+            WordCase::Title => write!(f, "title"),
+            WordCase::Upper => write!(f, "upper"),
+        }
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Result<PasswordPattern, anyhow::Error> {
+    pattern.parse()
+}
+
+pub(crate) fn parse_password_format(format: &str) -> Result<PasswordFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for PasswordFormat {
+    type Err = anyhow::Error;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "plain" => Ok(PasswordFormat::Plain),
+            "csv" => Ok(PasswordFormat::Csv),
+            "json" => Ok(PasswordFormat::Json),
+            v => anyhow::bail!("Unsupported password format {}", v),
+        }
+    }
+}
+
+impl fmt::Display for PasswordFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordFormat::Plain => write!(f, "plain"),
+            PasswordFormat::Csv => write!(f, "csv"),
+            PasswordFormat::Json => write!(f, "json"),
+        }
+    }
+}
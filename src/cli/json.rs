@@ -0,0 +1,59 @@
+use super::verify_file;
+use crate::{get_reader, sort_keys, CmdExecutor, JsonFilter};
+use clap::Parser;
+use serde_json::Value;
+use std::io::IsTerminal;
+
+#[derive(Debug, Parser)]
+pub struct JsonOpts {
+    #[arg(
+        value_parser = parse_filter,
+        default_value = ".",
+        help = "Filter such as '.items[] | select(.age > 30) | {name, email}'"
+    )]
+    pub filter: JsonFilter,
+
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(
+        long,
+        conflicts_with = "minify",
+        help = "Indent output (default on a terminal)"
+    )]
+    pub pretty: bool,
+
+    #[arg(long, help = "Print each result on a single line")]
+    pub minify: bool,
+
+    #[arg(long, help = "Sort object keys recursively")]
+    pub sort_keys: bool,
+
+    #[arg(short, long, help = "Print string results without quotes")]
+    pub raw_output: bool,
+}
+
+impl CmdExecutor for JsonOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let pretty = self.pretty || (!self.minify && std::io::stdout().is_terminal());
+        // a stream of values is accepted too, so NDJSON is filtered line by line
+        let values = serde_json::Deserializer::from_reader(get_reader(&self.input)?).into_iter();
+        for value in values {
+            for mut ret in self.filter.apply(&value?)? {
+                if self.sort_keys {
+                    sort_keys(&mut ret);
+                }
+                match ret {
+                    Value::String(s) if self.raw_output => println!("{}", s),
+                    ret if pretty => println!("{}", serde_json::to_string_pretty(&ret)?),
+                    ret => println!("{}", serde_json::to_string(&ret)?),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_filter(filter: &str) -> Result<JsonFilter, anyhow::Error> {
+    filter.parse()
+}
@@ -1,10 +1,18 @@
 mod b64;
+mod convert;
 mod csv;
 mod genpass;
 mod http;
+mod json;
+mod otp;
+mod password;
+mod schema;
 mod text;
 
-pub use self::{b64::*, csv::*, genpass::*, http::*, text::*};
+pub use self::{
+    b64::*, convert::*, csv::*, genpass::*, http::*, json::*, otp::*, password::*, schema::*,
+    text::*,
+};
 
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
@@ -20,15 +28,28 @@ pub struct Opts {
 #[enum_dispatch(CmdExecutor)]
 pub enum SubCommand {
     #[command(name = "csv", about = "Show csv, or convert CSV to other formats")]
-    Csv(CsvOpts),
+    Csv(Box<CsvOpts>),
     #[command(name = "genpass", about = "Generate a random password")]
-    GenPass(GenPassOpts),
+    GenPass(Box<GenPassOpts>),
     #[command(subcommand, about = "Base64 encode/decode")]
     Base64(Base64SubCommand),
     #[command(subcommand, about = "Text sign/verify")]
     Text(TextSubCommand),
     #[command(subcommand, about = "HTTP server")]
     Http(HttpSubCommand),
+    #[command(name = "json", about = "Query and reformat JSON with a jq-like filter")]
+    Json(JsonOpts),
+    #[command(subcommand, about = "Infer JSON Schemas from data samples")]
+    Schema(SchemaSubCommand),
+    #[command(
+        name = "convert",
+        about = "Convert config files among JSON, YAML, TOML, JSON5 and .env"
+    )]
+    Convert(ConvertOpts),
+    #[command(subcommand, about = "Check passwords you already have")]
+    Password(PasswordSubCommand),
+    #[command(subcommand, about = "One-time password secrets, codes and checks")]
+    Otp(OtpSubCommand),
 }
 
 fn verify_file(filename: &str) -> Result<String, &'static str> {
@@ -0,0 +1,218 @@
+use super::verify_file;
+use crate::{
+    encode_otp_secret, generate_otp_secret, get_content, render_qr, CmdExecutor, OtpKey, OtpParams,
+    MAX_OTP_WINDOW,
+};
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum OtpSubCommand {
+    #[command(about = "Generate a secret with its otpauth:// URI and QR code")]
+    Secret(OtpSecretOpts),
+
+    #[command(about = "Print the current TOTP code, or the HOTP code for a counter")]
+    Code(OtpCodeOpts),
+
+    #[command(about = "Check a code, allowing for clock drift")]
+    Verify(OtpVerifyOpts),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+// Unset options fall back to the key's otpauth:// URI, then to the usual SHA1, 6 digits, 30s
+#[derive(Debug, Clone, Parser)]
+pub struct OtpParamOpts {
+    #[arg(long, value_parser = parse_otp_algorithm, help = "sha1, sha256 or sha512")]
+    pub algorithm: Option<OtpAlgorithm>,
+
+    #[arg(long, value_parser = clap::value_parser!(u32).range(6..=8))]
+    pub digits: Option<u32>,
+
+    #[arg(long, help = "Seconds per TOTP code")]
+    pub period: Option<u64>,
+
+    #[arg(long, help = "Use HOTP at this counter instead of TOTP")]
+    pub counter: Option<u64>,
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpSecretOpts {
+    #[arg(
+        long,
+        default_value_t = 20,
+        value_parser = clap::value_parser!(u16).range(10..=64),
+        help = "Secret size in bytes"
+    )]
+    pub bytes: u16,
+
+    #[arg(long, help = "Service name shown in the authenticator app")]
+    pub issuer: Option<String>,
+
+    #[arg(
+        long,
+        default_value = "rcli",
+        help = "Account name shown in the authenticator app"
+    )]
+    pub account: String,
+
+    #[command(flatten)]
+    pub params: OtpParamOpts,
+
+    #[arg(long, help = "Leave out the terminal QR code")]
+    pub no_qr: bool,
+}
+
+impl CmdExecutor for OtpSecretOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let key = OtpKey {
+            secret: generate_otp_secret(self.bytes as usize),
+            params: self.params.apply(OtpParams::default()),
+        };
+        let uri = key.uri(self.issuer.as_deref(), &self.account);
+        println!("{}", encode_otp_secret(&key.secret));
+        println!("{}", uri);
+        if !self.no_qr {
+            println!("{}", render_qr(&uri)?);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpCodeOpts {
+    #[arg(
+        short,
+        long,
+        value_parser = verify_file,
+        help = "File holding the base32 secret or an otpauth:// URI"
+    )]
+    pub key: String,
+
+    #[command(flatten)]
+    pub params: OtpParamOpts,
+
+    #[arg(long, help = "Unix time to compute the TOTP code for (default: now)")]
+    pub time: Option<u64>,
+}
+
+impl CmdExecutor for OtpCodeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let key = load_key(&self.key, &self.params)?;
+        let time = self.time.map_or_else(now, Ok)?;
+        println!("{}", key.code(time)?);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct OtpVerifyOpts {
+    #[arg(
+        short,
+        long,
+        value_parser = verify_file,
+        help = "File holding the base32 secret or an otpauth:// URI"
+    )]
+    pub key: String,
+
+    #[command(flatten)]
+    pub params: OtpParamOpts,
+
+    #[arg(long, help = "Unix time to check the TOTP code at (default: now)")]
+    pub time: Option<u64>,
+
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(0..=MAX_OTP_WINDOW),
+        help = "Steps of drift to allow either way, or HOTP counters to look ahead (0-10)"
+    )]
+    pub window: u64,
+
+    pub code: String,
+}
+
+impl CmdExecutor for OtpVerifyOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let key = load_key(&self.key, &self.params)?;
+        let time = self.time.map_or_else(now, Ok)?;
+        match (
+            key.verify(&self.code, time, self.window)?,
+            key.params.counter,
+        ) {
+            (Some(offset), Some(counter)) => {
+                // verify only looks ahead of an HOTP counter, and never past u64::MAX
+                let used = counter
+                    .checked_add(offset as u64)
+                    .ok_or_else(|| anyhow::anyhow!("counter {} has no codes after it", counter))?;
+                let next = used.checked_add(1).ok_or_else(|| {
+                    anyhow::anyhow!("code verified at counter {}, the last one there is", used)
+                })?;
+                println!(
+                    "✓ Code verified at counter {}, continue from {}",
+                    used, next
+                )
+            }
+            (Some(0), None) => println!("✓ Code verified"),
+            (Some(offset), None) => println!("✓ Code verified, {} steps of drift", offset),
+            (None, _) => anyhow::bail!("code does not match"),
+        }
+        Ok(())
+    }
+}
+
+impl OtpParamOpts {
+    fn apply(&self, params: OtpParams) -> OtpParams {
+        OtpParams {
+            algorithm: self.algorithm.unwrap_or(params.algorithm),
+            digits: self.digits.unwrap_or(params.digits),
+            period: self.period.unwrap_or(params.period),
+            counter: self.counter.or(params.counter),
+        }
+    }
+}
+
+fn load_key(path: &str, params: &OtpParamOpts) -> anyhow::Result<OtpKey> {
+    let mut key = OtpKey::parse(&String::from_utf8(get_content(path)?)?)?;
+    key.params = params.apply(key.params);
+    Ok(key)
+}
+
+fn now() -> anyhow::Result<u64> {
+    Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+fn parse_otp_algorithm(algorithm: &str) -> Result<OtpAlgorithm, anyhow::Error> {
+    algorithm.parse()
+}
+
+impl FromStr for OtpAlgorithm {
+    type Err = anyhow::Error;
+    fn from_str(algorithm: &str) -> Result<Self, Self::Err> {
+        match algorithm.to_lowercase().as_str() {
+            "sha1" => Ok(OtpAlgorithm::Sha1),
+            "sha256" => Ok(OtpAlgorithm::Sha256),
+            "sha512" => Ok(OtpAlgorithm::Sha512),
+            v => anyhow::bail!("Unsupported OTP algorithm {}", v),
+        }
+    }
+}
+
+impl fmt::Display for OtpAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OtpAlgorithm::Sha1 => write!(f, "sha1"),
+            OtpAlgorithm::Sha256 => write!(f, "sha256"),
+            OtpAlgorithm::Sha512 => write!(f, "sha512"),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use super::{parse_password_format, verify_file};
+use crate::{
+    format_breach_reports, get_content, process_password_breached, process_password_check,
+    BreachList, CmdExecutor, PasswordFormat,
+};
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum PasswordSubCommand {
+    #[command(about = "Estimate how hard passwords are to guess with zxcvbn")]
+    Check(PasswordCheckOpts),
+
+    #[command(about = "Look passwords up in a local Have I Been Pwned download")]
+    Breached(PasswordBreachedOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct PasswordCheckOpts {
+    #[arg(
+        short,
+        long,
+        value_parser = verify_file,
+        default_value = "-",
+        help = "One password per line"
+    )]
+    pub input: String,
+
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "Words an attacker would try first, such as the username or company"
+    )]
+    pub context: Vec<String>,
+
+    #[arg(
+        long,
+        value_parser = parse_password_format,
+        default_value = "plain",
+        help = "plain, csv or json"
+    )]
+    pub format: PasswordFormat,
+}
+
+impl CmdExecutor for PasswordCheckOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let content = String::from_utf8(get_content(&self.input)?)?;
+        let ret = process_password_check(&content, &self.context, self.format)?;
+        print!("{}", ret);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreachHash {
+    Sha1,
+    Ntlm,
+}
+
+#[derive(Debug, Parser)]
+pub struct PasswordBreachedOpts {
+    #[arg(
+        short,
+        long,
+        value_parser = verify_file,
+        default_value = "-",
+        help = "One password per line"
+    )]
+    pub input: String,
+
+    #[arg(
+        long,
+        value_parser = verify_file,
+        help = "Sorted Have I Been Pwned HASH:COUNT file, as from the PwnedPasswordsDownloader"
+    )]
+    pub hibp: String,
+
+    #[arg(
+        long,
+        value_parser = parse_breach_hash,
+        help = "sha1 or ntlm (default: detected from the file)"
+    )]
+    pub hash: Option<BreachHash>,
+
+    #[arg(
+        long,
+        value_parser = parse_password_format,
+        default_value = "plain",
+        help = "plain, csv or json"
+    )]
+    pub format: PasswordFormat,
+}
+
+impl CmdExecutor for PasswordBreachedOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let list = BreachList::open(&self.hibp, self.hash)?;
+        let content = String::from_utf8(get_content(&self.input)?)?;
+        let reports = process_password_breached(&content, &list)?;
+        print!("{}", format_breach_reports(&reports, self.format)?);
+        // a failing exit status lets scripts refuse breached passwords
+        let breached = reports.iter().filter(|r| r.count > 0).count();
+        if breached > 0 {
+            anyhow::bail!(
+                "{} of {} passwords appear in known breaches",
+                breached,
+                reports.len()
+            )
+        }
+        Ok(())
+    }
+}
+
+fn parse_breach_hash(hash: &str) -> Result<BreachHash, anyhow::Error> {
+    hash.parse()
+}
+
+impl FromStr for BreachHash {
+    type Err = anyhow::Error;
+    fn from_str(hash: &str) -> Result<Self, Self::Err> {
+        match hash.to_lowercase().as_str() {
+            "sha1" | "sha-1" => Ok(BreachHash::Sha1),
+            "ntlm" => Ok(BreachHash::Ntlm),
+            v => anyhow::bail!("Unsupported breach hash {}", v),
+        }
+    }
+}
+
+impl fmt::Display for BreachHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BreachHash::Sha1 => write!(f, "sha1"),
+            BreachHash::Ntlm => write!(f, "ntlm"),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use super::verify_file;
+use crate::{
+    get_reader, is_spreadsheet, process_schema_infer_csv, process_schema_infer_json, CmdExecutor,
+    CsvTableReader, SpreadsheetReader, TableReader,
+};
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum SchemaSubCommand {
+    #[command(about = "Infer a JSON Schema (draft 2020-12) from a CSV or JSON/NDJSON sample")]
+    Infer(SchemaInferOpts),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SchemaInputFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Parser)]
+pub struct SchemaInferOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    #[arg(
+        long,
+        value_parser = parse_schema_input_format,
+        help = "csv or json, where json also accepts NDJSON (default: detected from the file extension)"
+    )]
+    pub input_format: Option<SchemaInputFormat>,
+
+    #[arg(short, long, default_value_t = ',')]
+    pub delimiter: char,
+
+    #[arg(
+        long,
+        default_value_t = 10,
+        help = "Describe string fields with at most this many distinct values as an enum"
+    )]
+    pub max_enum: usize,
+
+    #[arg(long, help = "Schema title (default: the input file name)")]
+    pub title: Option<String>,
+
+    #[arg(short, long, help = "Output file (default: stdout)")]
+    pub output: Option<String>,
+}
+
+impl CmdExecutor for SchemaInferOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let format = self
+            .input_format
+            .unwrap_or_else(|| detect_format(&self.input));
+        let title = self.title.or_else(|| {
+            Path::new(&self.input)
+                .file_stem()
+                .filter(|_| self.input != "-")
+                .map(|stem| stem.to_string_lossy().to_string())
+        });
+        let schema = match format {
+            SchemaInputFormat::Csv => {
+                let reader: Box<dyn TableReader> = if is_spreadsheet(&self.input) {
+                    Box::new(SpreadsheetReader::new(&self.input, None, None))
+                } else {
+                    Box::new(CsvTableReader::try_new(&self.input, self.delimiter)?)
+                };
+                process_schema_infer_csv(reader, self.max_enum, title.as_deref())?
+            }
+            SchemaInputFormat::Json => process_schema_infer_json(
+                get_reader(&self.input)?,
+                self.max_enum,
+                title.as_deref(),
+            )?,
+        };
+        let content = serde_json::to_string_pretty(&schema)?;
+        match self.output {
+            Some(output) => std::fs::write(output, content + "\n")?,
+            None => println!("{}", content),
+        }
+        Ok(())
+    }
+}
+
+fn detect_format(input: &str) -> SchemaInputFormat {
+    let extension = Path::new(input)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("json" | "ndjson" | "jsonl" | "geojson") => SchemaInputFormat::Json,
+        _ => SchemaInputFormat::Csv,
+    }
+}
+
+fn parse_schema_input_format(format: &str) -> Result<SchemaInputFormat, anyhow::Error> {
+    format.parse()
+}
+
+impl FromStr for SchemaInputFormat {
+    type Err = anyhow::Error;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_lowercase().as_str() {
+            "csv" => Ok(SchemaInputFormat::Csv),
+            "json" | "ndjson" => Ok(SchemaInputFormat::Json),
+            v => anyhow::bail!("Unsupported schema input format {}", v),
+        }
+    }
+}
+
+impl fmt::Display for SchemaInputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaInputFormat::Csv => write!(f, "csv"),
+            SchemaInputFormat::Json => write!(f, "json"),
+        }
+    }
+}
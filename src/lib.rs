@@ -15,3 +15,10 @@ use enum_dispatch::enum_dispatch;
 pub trait CmdExecutor {
     async fn execute(self) -> Result<()>;
 }
+
+// Large options are boxed in the subcommand enum to keep it small
+impl<T: CmdExecutor> CmdExecutor for Box<T> {
+    async fn execute(self) -> Result<()> {
+        (*self).execute().await
+    }
+}
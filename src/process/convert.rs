@@ -0,0 +1,382 @@
+use crate::ConfigFormat;
+use anyhow::Result;
+use serde_json::{Map, Number, Value};
+
+// Documents are read into a json value, then checked against what the target format can hold
+pub fn process_convert(content: &str, from: ConfigFormat, to: ConfigFormat) -> Result<String> {
+    let value = parse_config(content, from)?;
+    write_config(&value, to)
+}
+
+pub fn parse_config(content: &str, format: ConfigFormat) -> Result<Value> {
+    let value = match format {
+        ConfigFormat::Json => serde_json::from_str(content)?,
+        ConfigFormat::Json5 => json5::from_str(content)?,
+        ConfigFormat::Yaml => from_yaml(serde_yaml::from_str(content)?, "")?,
+        ConfigFormat::Toml => from_toml(toml::Value::Table(content.parse()?), "")?,
+        ConfigFormat::Dotenv => Value::Object(parse_dotenv(content)?),
+    };
+    Ok(value)
+}
+
+pub fn write_config(value: &Value, format: ConfigFormat) -> Result<String> {
+    let content = match format {
+        ConfigFormat::Json => serde_json::to_string_pretty(value)? + "\n",
+        ConfigFormat::Json5 => {
+            let mut out = String::new();
+            write_json5(value, 0, &mut out);
+            out + "\n"
+        }
+        ConfigFormat::Yaml => serde_yaml::to_string(value)?,
+        ConfigFormat::Toml => match to_toml(value, "")? {
+            toml::Value::Table(table) => toml::to_string_pretty(&table)?,
+            other => anyhow::bail!(
+                "TOML documents must be a table at the root, found {}",
+                other.type_str()
+            ),
+        },
+        ConfigFormat::Dotenv => write_dotenv(value)?,
+    };
+    Ok(content)
+}
+
+fn child(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn at(path: &str) -> String {
+    if path.is_empty() {
+        "the root".to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "nested map",
+    }
+}
+
+fn float(f: f64, path: &str) -> Result<Value> {
+    Number::from_f64(f)
+        .map(Value::Number)
+        .ok_or_else(|| anyhow::anyhow!("{} at {} is not a finite number", f, at(path)))
+}
+
+fn from_yaml(value: serde_yaml::Value, path: &str) -> Result<Value> {
+    use serde_yaml::Value as Yaml;
+    let value = match value {
+        Yaml::Null => Value::Null,
+        Yaml::Bool(b) => Value::Bool(b),
+        Yaml::Number(n) => match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(i), _, _) => i.into(),
+            (_, Some(u), _) => u.into(),
+            (_, _, Some(f)) => float(f, path)?,
+            _ => anyhow::bail!("unsupported number {} at {}", n, at(path)),
+        },
+        Yaml::String(s) => Value::String(s),
+        Yaml::Sequence(items) => items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| from_yaml(item, &format!("{}[{}]", path, i)))
+            .collect::<Result<_>>()?,
+        Yaml::Mapping(mapping) => {
+            let mut map = Map::new();
+            for (key, value) in mapping {
+                // scalar keys become strings, as they would in json
+                let key = match key {
+                    Yaml::String(s) => s,
+                    Yaml::Bool(b) => b.to_string(),
+                    Yaml::Number(n) => n.to_string(),
+                    _ => anyhow::bail!("map keys at {} must be scalars", at(path)),
+                };
+                let value = from_yaml(value, &child(path, &key))?;
+                map.insert(key, value);
+            }
+            Value::Object(map)
+        }
+        Yaml::Tagged(tagged) => from_yaml(tagged.value, path)?,
+    };
+    Ok(value)
+}
+
+fn from_toml(value: toml::Value, path: &str) -> Result<Value> {
+    let value = match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => float(f, path)?,
+        toml::Value::Boolean(b) => Value::Bool(b),
+        // other formats have no date type, so dates are kept as their RFC 3339 text
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(items) => items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| from_toml(item, &format!("{}[{}]", path, i)))
+            .collect::<Result<_>>()?,
+        toml::Value::Table(table) => table
+            .into_iter()
+            .map(|(key, value)| {
+                let value = from_toml(value, &child(path, &key))?;
+                Ok((key, value))
+            })
+            .collect::<Result<Map<_, _>>>()
+            .map(Value::Object)?,
+    };
+    Ok(value)
+}
+
+fn to_toml(value: &Value, path: &str) -> Result<toml::Value> {
+    let value = match value {
+        Value::Null => anyhow::bail!(
+            "null at {} cannot be represented in TOML, which has no null value; remove the key or give it a value",
+            at(path)
+        ),
+        Value::Bool(b) => toml::Value::Boolean(*b),
+        Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => toml::Value::Integer(i),
+            (None, Some(_)) if n.is_u64() => anyhow::bail!(
+                "{} at {} does not fit in a TOML integer (64-bit signed)",
+                n,
+                at(path)
+            ),
+            (None, Some(f)) => toml::Value::Float(f),
+            _ => anyhow::bail!("unsupported number {} at {}", n, at(path)),
+        },
+        Value::String(s) => toml::Value::String(s.clone()),
+        Value::Array(items) => toml::Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| to_toml(item, &format!("{}[{}]", path, i)))
+                .collect::<Result<_>>()?,
+        ),
+        Value::Object(map) => toml::Value::Table(
+            map.iter()
+                .map(|(key, value)| Ok((key.clone(), to_toml(value, &child(path, key))?)))
+                .collect::<Result<_>>()?,
+        ),
+    };
+    Ok(value)
+}
+
+fn is_env_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// KEY=value lines with optional `export`, # comments, and single (literal) or double quotes
+fn parse_dotenv(content: &str) -> Result<Map<String, Value>> {
+    let mut map = Map::new();
+    for (n, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("line {}: expected KEY=value", n + 1))?;
+        let key = key.trim();
+        if !is_env_key(key) {
+            anyhow::bail!("line {}: {:?} is not a valid variable name", n + 1, key);
+        }
+        let value = value.trim();
+        let unterminated = || anyhow::anyhow!("line {}: unterminated quote in {}", n + 1, key);
+        let value = if let Some(rest) = value.strip_prefix('\'') {
+            let end = rest.find('\'').ok_or_else(unterminated)?;
+            rest[..end].to_string()
+        } else if let Some(rest) = value.strip_prefix('"') {
+            let mut out = String::new();
+            let mut chars = rest.chars();
+            loop {
+                match chars.next().ok_or_else(unterminated)? {
+                    '"' => break,
+                    '\\' => match chars.next().ok_or_else(unterminated)? {
+                        'n' => out.push('\n'),
+                        't' => out.push('\t'),
+                        'r' => out.push('\r'),
+                        c => out.push(c),
+                    },
+                    c => out.push(c),
+                }
+            }
+            out
+        } else {
+            // an unquoted value ends at a comment
+            match value.find(" #") {
+                Some(i) => value[..i].trim_end().to_string(),
+                None => value.to_string(),
+            }
+        };
+        map.insert(key.to_string(), Value::String(value));
+    }
+    Ok(map)
+}
+
+fn write_dotenv(value: &Value) -> Result<String> {
+    let Value::Object(map) = value else {
+        anyhow::bail!(
+            ".env files hold KEY=value pairs, so the root must be a map, found {}",
+            kind(value)
+        );
+    };
+    let mut out = String::new();
+    for (key, value) in map {
+        if !is_env_key(key) {
+            anyhow::bail!(
+                "{:?} is not a valid .env variable name (letters, digits and _ only)",
+                key
+            );
+        }
+        let value = match value {
+            Value::String(s) => quote_env(s),
+            Value::Bool(_) | Value::Number(_) => value.to_string(),
+            Value::Null => anyhow::bail!(
+                "null at {} cannot be represented in .env, which only holds strings",
+                key
+            ),
+            _ => anyhow::bail!(
+                "{} at {} cannot be represented in .env, which only holds flat KEY=value pairs",
+                kind(value),
+                key
+            ),
+        };
+        out.push_str(&format!("{}={}\n", key, value));
+    }
+    Ok(out)
+}
+
+fn quote_env(s: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-.,:/@+".contains(c);
+    if !s.is_empty() && s.chars().all(plain) {
+        s.to_string()
+    } else if !s.contains(['\'', '\n']) {
+        format!("'{}'", s)
+    } else {
+        let escaped = s
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n");
+        format!("\"{}\"", escaped)
+    }
+}
+
+// Pretty JSON with identifier keys left unquoted and trailing commas, as JSON5 allows
+fn write_json5(value: &Value, indent: usize, out: &mut String) {
+    let pad = "  ".repeat(indent + 1);
+    match value {
+        Value::Array(items) if !items.is_empty() => {
+            out.push_str("[\n");
+            for item in items {
+                out.push_str(&pad);
+                write_json5(item, indent + 1, out);
+                out.push_str(",\n");
+            }
+            out.push_str(&"  ".repeat(indent));
+            out.push(']');
+        }
+        Value::Object(map) if !map.is_empty() => {
+            out.push_str("{\n");
+            for (key, value) in map {
+                out.push_str(&pad);
+                let identifier = key
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '$')
+                    && key
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$');
+                if identifier {
+                    out.push_str(key);
+                } else {
+                    out.push_str(&Value::String(key.clone()).to_string());
+                }
+                out.push_str(": ");
+                write_json5(value, indent + 1, out);
+                out.push_str(",\n");
+            }
+            out.push_str(&"  ".repeat(indent));
+            out.push('}');
+        }
+        value => out.push_str(&value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = r#"
+name: rcli
+server:
+  host: 0.0.0.0
+  port: 8080
+  tags: [a, b]
+debug: false
+"#;
+
+    #[test]
+    fn test_convert_round_trip() -> Result<()> {
+        let expected = parse_config(YAML, ConfigFormat::Yaml)?;
+        for format in [
+            ConfigFormat::Json,
+            ConfigFormat::Json5,
+            ConfigFormat::Toml,
+            ConfigFormat::Yaml,
+        ] {
+            let content = write_config(&expected, format)?;
+            assert_eq!(parse_config(&content, format)?, expected, "{}", format);
+        }
+        let toml = process_convert(YAML, ConfigFormat::Yaml, ConfigFormat::Toml)?;
+        assert!(toml.starts_with("name = \"rcli\"\ndebug = false\n"));
+        assert!(toml.contains("[server]\nhost = \"0.0.0.0\"\nport = 8080\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_unrepresentable() {
+        let err = process_convert("a: {b: [1, null]}", ConfigFormat::Yaml, ConfigFormat::Toml)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("null at a.b[1] cannot be represented in TOML"));
+        let err = process_convert("[1, 2]", ConfigFormat::Json, ConfigFormat::Toml).unwrap_err();
+        assert!(err.to_string().contains("root"));
+        let err = process_convert(YAML, ConfigFormat::Yaml, ConfigFormat::Dotenv)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("nested map at server cannot be represented in .env"));
+    }
+
+    #[test]
+    fn test_dotenv() -> Result<()> {
+        let content = r#"
+# database
+export DB_HOST=localhost # local only
+DB_PASS='p@ss #1'
+GREETING="hello\n\"world\""
+PORT=5432
+"#;
+        let value = parse_config(content, ConfigFormat::Dotenv)?;
+        assert_eq!(value["DB_HOST"], "localhost");
+        assert_eq!(value["DB_PASS"], "p@ss #1");
+        assert_eq!(value["GREETING"], "hello\n\"world\"");
+        assert_eq!(value["PORT"], "5432");
+        let written = write_config(&value, ConfigFormat::Dotenv)?;
+        assert_eq!(parse_config(&written, ConfigFormat::Dotenv)?, value);
+        assert!(parse_config("not a pair", ConfigFormat::Dotenv).is_err());
+        Ok(())
+    }
+}
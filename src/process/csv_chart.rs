@@ -0,0 +1,238 @@
+use crate::{column_indices, format_number, ChartKind, TableReader};
+use anyhow::Result;
+use std::collections::HashMap;
+
+// Partial blocks in eighths, used to draw horizontal bars at sub-character precision
+const BAR_BLOCKS: [char; 8] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+const SPARK_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub fn process_csv_chart(
+    reader: Box<dyn TableReader>,
+    column: &str,
+    group_by: Option<&str>,
+    kind: ChartKind,
+    bins: usize,
+    width: usize,
+) -> Result<String> {
+    let (headers, records) = reader.read()?;
+    let mut names = vec![column.to_string()];
+    names.extend(group_by.map(String::from));
+    let indices = column_indices(&headers, &names)?;
+
+    // groups keep the order in which they first appear
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    let mut group_pos: HashMap<String, usize> = HashMap::new();
+    for record in records {
+        let record = record?;
+        let value = record.get(indices[0]).unwrap_or_default().to_string();
+        let group = indices
+            .get(1)
+            .map(|&i| record.get(i).unwrap_or_default().to_string())
+            .unwrap_or_default();
+        let pos = *group_pos.entry(group.clone()).or_insert_with(|| {
+            groups.push((group, Vec::new()));
+            groups.len() - 1
+        });
+        groups[pos].1.push(value);
+    }
+
+    let mut ret = String::new();
+    match kind {
+        ChartKind::Bar => {
+            for (group, values) in &groups {
+                push_title(&mut ret, group_by, group);
+                ret.push_str(&bar_chart(values, width));
+            }
+        }
+        ChartKind::Histogram => {
+            let numbers = groups
+                .iter()
+                .map(|(group, values)| Ok((group, parse_numbers(column, values)?)))
+                .collect::<Result<Vec<_>>>()?;
+            // every group shares the same bin edges so the charts can be compared
+            let all = numbers.iter().flat_map(|(_, n)| n.iter().copied());
+            let Some((min, max)) = all.fold(None, |acc: Option<(f64, f64)>, n| {
+                Some(acc.map_or((n, n), |(lo, hi)| (lo.min(n), hi.max(n))))
+            }) else {
+                anyhow::bail!("column {} has no numeric values to chart", column);
+            };
+            for (group, numbers) in &numbers {
+                push_title(&mut ret, group_by, group);
+                ret.push_str(&histogram(numbers, min, max, bins, width));
+            }
+        }
+        ChartKind::Sparkline => {
+            let label_width = groups.iter().map(|(g, _)| g.chars().count()).max();
+            for (group, values) in &groups {
+                let numbers = parse_numbers(column, values)?;
+                if group_by.is_some() {
+                    let w = label_width.unwrap_or(0);
+                    ret.push_str(&format!("{:>w$} │", group, w = w));
+                }
+                ret.push_str(&sparkline(&numbers, width));
+                ret.push('\n');
+            }
+        }
+    }
+    Ok(ret)
+}
+
+fn push_title(ret: &mut String, group_by: Option<&str>, group: &str) {
+    if let Some(name) = group_by {
+        if !ret.is_empty() {
+            ret.push('\n');
+        }
+        ret.push_str(&format!("{} = {}\n", name, group));
+    }
+}
+
+// Empty cells are skipped, anything else that is not a number is an error
+fn parse_numbers(column: &str, values: &[String]) -> Result<Vec<f64>> {
+    values
+        .iter()
+        .filter(|v| !v.trim().is_empty())
+        .map(|v| {
+            v.trim().parse::<f64>().map_err(|_| {
+                anyhow::anyhow!(
+                    "cannot chart non-numeric value {:?} in {}, try --kind bar",
+                    v,
+                    column
+                )
+            })
+        })
+        .collect()
+}
+
+fn bar(value: usize, max: usize, width: usize) -> String {
+    if max == 0 {
+        return String::new();
+    }
+    let eighths = value * width * 8 / max;
+    let mut ret = "█".repeat(eighths / 8);
+    if let Some(&block) = (eighths % 8).checked_sub(1).and_then(|i| BAR_BLOCKS.get(i)) {
+        ret.push(block);
+    }
+    ret
+}
+
+// Render labelled rows as "label │bar count", with labels padded to the same width
+fn render_rows(rows: &[(String, usize)], width: usize) -> String {
+    let label_width = rows
+        .iter()
+        .map(|(l, _)| l.chars().count())
+        .max()
+        .unwrap_or(0);
+    let max = rows.iter().map(|(_, c)| *c).max().unwrap_or(0);
+    rows.iter()
+        .map(|(label, count)| {
+            format!(
+                "{:>w$} │{} {}\n",
+                label,
+                bar(*count, max, width),
+                count,
+                w = label_width
+            )
+        })
+        .collect()
+}
+
+fn bar_chart(values: &[String], width: usize) -> String {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    let mut pos: HashMap<&str, usize> = HashMap::new();
+    for value in values {
+        match pos.get(value.as_str()) {
+            Some(&i) => counts[i].1 += 1,
+            None => {
+                pos.insert(value, counts.len());
+                counts.push((value.clone(), 1));
+            }
+        }
+    }
+    // most frequent first, ties keep their first-appearance order
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    render_rows(&counts, width)
+}
+
+fn histogram(numbers: &[f64], min: f64, max: f64, bins: usize, width: usize) -> String {
+    let step = (max - min) / bins as f64;
+    let mut counts = vec![0; bins];
+    for &n in numbers {
+        let bin = if step == 0.0 {
+            0
+        } else {
+            (((n - min) / step) as usize).min(bins - 1)
+        };
+        counts[bin] += 1;
+    }
+    let round = |n: f64| format_number((n * 100.0).round() / 100.0);
+    let rows = counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let lo = min + step * i as f64;
+            let close = if i + 1 == bins { ']' } else { ')' };
+            (
+                format!("[{}, {}{}", round(lo), round(lo + step), close),
+                count,
+            )
+        })
+        .collect::<Vec<_>>();
+    render_rows(&rows, width)
+}
+
+// Values beyond the width are averaged into buckets so the line fits the terminal
+fn sparkline(numbers: &[f64], width: usize) -> String {
+    if numbers.is_empty() {
+        return String::new();
+    }
+    let points = if numbers.len() > width {
+        (0..width)
+            .map(|i| {
+                let chunk = &numbers[i * numbers.len() / width..(i + 1) * numbers.len() / width];
+                chunk.iter().sum::<f64>() / chunk.len() as f64
+            })
+            .collect::<Vec<_>>()
+    } else {
+        numbers.to_vec()
+    };
+    let min = points.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = points.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    points
+        .iter()
+        .map(|&n| {
+            let level = if max > min {
+                ((n - min) / (max - min) * 7.0).round() as usize
+            } else {
+                3
+            };
+            SPARK_BLOCKS[level]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(10, 10, 4), "████");
+        assert_eq!(bar(5, 10, 3), "█▌");
+        assert_eq!(bar(0, 10, 4), "");
+    }
+
+    #[test]
+    fn test_histogram_and_bar_chart() {
+        let hist = histogram(&[1.0, 2.0, 2.5, 4.0], 1.0, 4.0, 3, 4);
+        assert_eq!(hist, "[1, 2) │██ 1\n[2, 3) │████ 2\n[3, 4] │██ 1\n");
+        let values = ["GK", "DF", "GK", "FW", "GK"].map(String::from);
+        assert_eq!(bar_chart(&values, 3), "GK │███ 3\nDF │█ 1\nFW │█ 1\n");
+    }
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[1.0, 2.0, 3.0, 8.0], 10), "▁▂▃█");
+        assert_eq!(sparkline(&[0.0, 0.0, 7.0, 7.0], 2), "▁█");
+        assert_eq!(sparkline(&[5.0, 5.0], 10), "▄▄");
+    }
+}
@@ -1,22 +1,173 @@
-use crate::cli::OutputFormat;
+use crate::{
+    cli::OutputFormat, get_reader, read_csv_parallel, read_csv_slice, records_to_json_parallel,
+    records_to_yaml_parallel, thread_pool, RowSlice,
+};
 use anyhow::Result;
-use csv::Reader;
+use csv::{ReaderBuilder, StringRecord};
+use rayon::prelude::*;
 use serde_json::Value;
 use std::fs;
 
-pub fn process_csv(input: &str, output: String, format: OutputFormat) -> Result<()> {
-    let mut reader = Reader::from_path(input)?;
-    let mut ret = Vec::with_capacity(128);
-    let headers = reader.headers()?.clone();
-    for result in reader.records() {
-        let record = result?;
-        let deserialize_value = headers.iter().zip(record.iter()).collect::<Value>();
-        ret.push(deserialize_value);
-    }
-    let content = match format {
-        OutputFormat::Json => serde_json::to_string(&ret)?,
-        OutputFormat::Yaml => serde_json::to_string(&ret)?,
-    };
+pub type Records<'a> = Box<dyn Iterator<Item = Result<StringRecord>> + 'a>;
+
+pub trait RecordTransform {
+    // Consume the records stream and return the transformed header and stream
+    fn transform<'a>(
+        self: Box<Self>,
+        headers: StringRecord,
+        records: Records<'a>,
+    ) -> Result<(StringRecord, Records<'a>)>;
+}
+
+pub trait TableReader {
+    // Read the header row and return it with a stream of the remaining records
+    fn read(self: Box<Self>) -> Result<(StringRecord, Records<'static>)>;
+}
+
+pub struct CsvTableReader {
+    input: String,
+    delimiter: u8,
+    slice: Option<RowSlice>,
+    threads: usize,
+}
+
+impl CsvTableReader {
+    pub fn try_new(input: &str, delimiter: char) -> Result<Self> {
+        Ok(Self {
+            input: input.to_string(),
+            delimiter: delimiter_byte(delimiter)?,
+            slice: None,
+            threads: 1,
+        })
+    }
+
+    pub fn with_slice(mut self, slice: Option<RowSlice>) -> Self {
+        self.slice = slice;
+        self
+    }
+
+    // 0 uses every core, 1 keeps the sequential reader
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+}
+
+impl TableReader for CsvTableReader {
+    fn read(self: Box<Self>) -> Result<(StringRecord, Records<'static>)> {
+        if let Some(slice) = self.slice {
+            return read_csv_slice(&self.input, self.delimiter, slice);
+        }
+        if self.threads != 1 && self.input != "-" {
+            return read_csv_parallel(&self.input, self.delimiter, self.threads);
+        }
+        let mut reader = ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .from_reader(get_reader(&self.input)?);
+        let headers = reader.headers()?.clone();
+        Ok((headers, Box::new(reader.into_records().map(|r| Ok(r?)))))
+    }
+}
+
+pub trait TableWriter {
+    // Render the header and records into the output document
+    fn write(&self, headers: &StringRecord, records: Records) -> Result<String>;
+}
+
+pub struct ValueWriter {
+    format: OutputFormat,
+    threads: usize,
+    missing: Option<Vec<String>>,
+}
+
+impl ValueWriter {
+    pub fn new(format: OutputFormat) -> Self {
+        Self {
+            format,
+            threads: 1,
+            missing: None,
+        }
+    }
+
+    // Cells equal to one of the missing markers are written as null
+    pub fn with_missing(mut self, missing: Option<Vec<String>>) -> Self {
+        self.missing = missing;
+        self
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+}
+
+impl TableWriter for ValueWriter {
+    fn write(&self, headers: &StringRecord, records: Records) -> Result<String> {
+        let missing = self.missing.as_deref().unwrap_or_default();
+        let to_value = |record: &StringRecord| {
+            headers
+                .iter()
+                .zip(record.iter())
+                .map(|(k, v)| match missing.iter().any(|m| m == v) {
+                    true => (k, Value::Null),
+                    false => (k, Value::from(v)),
+                })
+                .collect::<Value>()
+        };
+        let content = if self.threads == 1 {
+            let mut ret = Vec::with_capacity(128);
+            for result in records {
+                ret.push(to_value(&result?));
+            }
+            match self.format {
+                OutputFormat::Json => serde_json::to_string(&ret)?,
+                OutputFormat::Yaml => serde_yaml::to_string(&ret)?,
+                v => anyhow::bail!("{} output is not a key-value format", v),
+            }
+        } else {
+            let pool = thread_pool(self.threads)?;
+            let records = records.collect::<Result<Vec<_>>>()?;
+            let ret = pool.install(|| records.par_iter().map(to_value).collect::<Vec<_>>());
+            match self.format {
+                OutputFormat::Json => records_to_json_parallel(&ret, &pool)?,
+                OutputFormat::Yaml => records_to_yaml_parallel(&ret, &pool)?,
+                v => anyhow::bail!("{} output is not a key-value format", v),
+            }
+        };
+        Ok(content)
+    }
+}
+
+pub fn process_csv(
+    reader: Box<dyn TableReader>,
+    output: String,
+    writer: Box<dyn TableWriter>,
+    transforms: Vec<Box<dyn RecordTransform>>,
+) -> Result<()> {
+    let (mut headers, mut records) = reader.read()?;
+    for transform in transforms {
+        (headers, records) = transform.transform(headers, records)?;
+    }
+    let content = writer.write(&headers, records)?;
     fs::write(output, content)?;
     Ok(())
 }
+
+pub fn delimiter_byte(delimiter: char) -> Result<u8> {
+    if !delimiter.is_ascii() {
+        anyhow::bail!("delimiter must be an ASCII character");
+    }
+    Ok(delimiter as u8)
+}
+
+pub fn column_indices(headers: &StringRecord, columns: &[String]) -> Result<Vec<usize>> {
+    columns
+        .iter()
+        .map(|col| {
+            headers
+                .iter()
+                .position(|h| h == col)
+                .ok_or_else(|| anyhow::anyhow!("column {} not found in header", col))
+        })
+        .collect()
+}
@@ -0,0 +1,395 @@
+use crate::{generate_password, get_reader, parse_date, PasswordPolicy};
+use anyhow::Result;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Duration, NaiveDate};
+use csv::{ReaderBuilder, Writer};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+
+#[rustfmt::skip]
+const FIRST_NAMES: &[&str] = &[
+    "Alessandro", "Andrea", "Anna", "Beatrice", "Carlo", "Chiara", "Daniele", "Elena", "Federico",
+    "Francesca", "Giorgio", "Giulia", "Leonardo", "Lorenzo", "Lucia", "Marco", "Maria", "Matteo",
+    "Paolo", "Sara", "Simone", "Sofia", "Stefano", "Valentina",
+];
+#[rustfmt::skip]
+const LAST_NAMES: &[&str] = &[
+    "Bianchi", "Bruno", "Colombo", "Conti", "Costa", "De Luca", "Esposito", "Ferrari", "Gallo",
+    "Greco", "Lombardi", "Marino", "Mancini", "Moretti", "Ricci", "Romano", "Rossi", "Russo",
+    "Santoro", "Fontana",
+];
+const EMAIL_DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+#[rustfmt::skip]
+const LOREM: &[&str] = &[
+    "lorem", "ipsum", "dolor", "sit", "amet", "consectetur", "adipiscing", "elit", "sed", "do",
+    "eiusmod", "tempor", "incididunt", "ut", "labore", "et", "dolore", "magna", "aliqua", "enim",
+    "ad", "minim", "veniam", "quis", "nostrud", "exercitation", "ullamco", "laboris", "nisi",
+    "aliquip", "ex", "ea", "commodo", "consequat",
+];
+
+#[derive(Debug, Deserialize)]
+pub struct FakeSchema {
+    pub columns: Vec<FakeColumn>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FakeColumn {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: FakeKind,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FakeKind {
+    Name,
+    Email,
+    Sequence {
+        #[serde(default = "default_sequence_start")]
+        start: i64,
+    },
+    Int {
+        #[serde(default)]
+        min: i64,
+        #[serde(default = "default_int_max")]
+        max: i64,
+    },
+    Date {
+        #[serde(default = "default_date_start")]
+        start: String,
+        #[serde(default = "default_date_end")]
+        end: String,
+        #[serde(default = "default_date_format")]
+        format: String,
+    },
+    Enum {
+        values: Vec<String>,
+    },
+    Uuid,
+    Lorem {
+        #[serde(default = "default_lorem_words")]
+        words: usize,
+    },
+    // Values are drawn from a column of another csv, relative paths resolve against the schema
+    ForeignKey {
+        file: String,
+        column: String,
+    },
+    Password {
+        #[serde(default = "default_password_length")]
+        length: usize,
+    },
+}
+
+fn default_sequence_start() -> i64 {
+    1
+}
+
+fn default_int_max() -> i64 {
+    100
+}
+
+fn default_date_start() -> String {
+    "2000-01-01".into()
+}
+
+fn default_date_end() -> String {
+    "2024-12-31".into()
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".into()
+}
+
+fn default_lorem_words() -> usize {
+    8
+}
+
+fn default_password_length() -> usize {
+    16
+}
+
+impl FakeSchema {
+    pub fn load(path: &str) -> Result<Self> {
+        let mut schema: Self = serde_yaml::from_reader(get_reader(path)?)?;
+        if schema.columns.is_empty() {
+            anyhow::bail!("fake schema must declare at least one column");
+        }
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        for column in schema.columns.iter_mut() {
+            if let FakeKind::ForeignKey { file, .. } = &mut column.kind {
+                if path != "-" && Path::new(file.as_str()).is_relative() {
+                    *file = dir.join(&file).to_string_lossy().to_string();
+                }
+            }
+        }
+        Ok(schema)
+    }
+}
+
+// A column kind with its inputs validated and loaded once, ready to draw values from
+enum Generator {
+    Name,
+    Email,
+    Sequence(i64),
+    Int(i64, i64),
+    Date(NaiveDate, i64, String),
+    Choice(Vec<String>),
+    Uuid,
+    Lorem(usize),
+    Password(usize),
+}
+
+impl Generator {
+    fn try_new(column: &FakeColumn) -> Result<Self> {
+        let generator = match &column.kind {
+            FakeKind::Name => Generator::Name,
+            FakeKind::Email => Generator::Email,
+            FakeKind::Sequence { start } => Generator::Sequence(*start),
+            FakeKind::Int { min, max } if min > max => {
+                anyhow::bail!(
+                    "column {}: min {} is greater than max {}",
+                    column.name,
+                    min,
+                    max
+                )
+            }
+            FakeKind::Int { min, max } => Generator::Int(*min, *max),
+            FakeKind::Date { start, end, format } => {
+                let date = |s: &str| {
+                    parse_date(s).ok_or_else(|| {
+                        anyhow::anyhow!("column {}: {:?} is not a date", column.name, s)
+                    })
+                };
+                let (start, end) = (date(start)?, date(end)?);
+                if start > end {
+                    anyhow::bail!("column {}: start is after end", column.name);
+                }
+                // formatting fails, rather than yielding text, for unknown or time specifiers
+                let mut probe = String::new();
+                if StrftimeItems::new(format).any(|item| item == Item::Error)
+                    || write!(probe, "{}", start.format(format)).is_err()
+                {
+                    anyhow::bail!("column {}: {:?} is not a date format", column.name, format);
+                }
+                Generator::Date(start, (end - start).num_days(), format.clone())
+            }
+            FakeKind::Enum { values } if values.is_empty() => {
+                anyhow::bail!("column {}: enum needs at least one value", column.name)
+            }
+            FakeKind::Enum { values } => Generator::Choice(values.clone()),
+            FakeKind::Uuid => Generator::Uuid,
+            FakeKind::Lorem { words } => Generator::Lorem(*words),
+            FakeKind::ForeignKey { file, column: key } => {
+                let mut reader = ReaderBuilder::new().from_path(file)?;
+                let i = reader
+                    .headers()?
+                    .iter()
+                    .position(|h| h == key)
+                    .ok_or_else(|| anyhow::anyhow!("column {} not found in {}", key, file))?;
+                let keys = reader
+                    .records()
+                    .map(|r| Ok(r?.get(i).unwrap_or_default().to_string()))
+                    .collect::<Result<Vec<_>>>()?;
+                if keys.is_empty() {
+                    anyhow::bail!("column {}: {} has no rows to reference", column.name, file);
+                }
+                Generator::Choice(keys)
+            }
+            FakeKind::Password { length } => Generator::Password(*length),
+        };
+        Ok(generator)
+    }
+
+    fn generate(&self, row: usize, rng: &mut StdRng) -> Result<String> {
+        let value = match self {
+            Generator::Name => format!("{} {}", pick(FIRST_NAMES, rng), pick(LAST_NAMES, rng)),
+            Generator::Email => format!(
+                "{}.{}{}@{}",
+                pick(FIRST_NAMES, rng).to_lowercase(),
+                pick(LAST_NAMES, rng).to_lowercase().replace(' ', ""),
+                rng.gen_range(1..100),
+                pick(EMAIL_DOMAINS, rng)
+            ),
+            Generator::Sequence(start) => i64::try_from(row)
+                .ok()
+                .and_then(|row| start.checked_add(row))
+                .ok_or_else(|| anyhow::anyhow!("sequence from {} overflows at row {}", start, row))?
+                .to_string(),
+            Generator::Int(min, max) => rng.gen_range(*min..=*max).to_string(),
+            Generator::Date(start, days, format) => {
+                let date = *start + Duration::days(rng.gen_range(0..=*days));
+                date.format(format).to_string()
+            }
+            Generator::Choice(values) => values.choose(rng).cloned().unwrap_or_default(),
+            Generator::Uuid => uuid_v4(rng.gen()),
+            Generator::Lorem(words) => {
+                let mut text = (0..*words)
+                    .map(|_| pick(LOREM, rng))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if let Some(first) = text.get_mut(..1) {
+                    first.make_ascii_uppercase();
+                }
+                if !text.is_empty() {
+                    text.push('.');
+                }
+                text
+            }
+            Generator::Password(length) => generate_password(
+                rng,
+                &PasswordPolicy {
+                    length: *length,
+                    ..Default::default()
+                },
+            )?,
+        };
+        Ok(value)
+    }
+}
+
+fn pick<'a>(values: &[&'a str], rng: &mut StdRng) -> &'a str {
+    values.choose(rng).copied().unwrap_or_default()
+}
+
+// Random bytes with the RFC 4122 version 4 and variant bits set
+fn uuid_v4(mut bytes: [u8; 16]) -> String {
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = bytes
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+pub fn process_csv_fake(
+    schema: &FakeSchema,
+    rows: usize,
+    seed: Option<u64>,
+    output: impl Write,
+) -> Result<()> {
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let generators = schema
+        .columns
+        .iter()
+        .map(Generator::try_new)
+        .collect::<Result<Vec<_>>>()?;
+    let mut writer = Writer::from_writer(output);
+    writer.write_record(schema.columns.iter().map(|c| c.name.as_str()))?;
+    for row in 0..rows {
+        let record = generators
+            .iter()
+            .map(|g| g.generate(row, &mut rng))
+            .collect::<Result<Vec<_>>>()?;
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+columns:
+  - name: id
+    kind: sequence
+  - name: name
+    kind: name
+  - name: email
+    kind: email
+  - name: age
+    kind: int
+    min: 18
+    max: 40
+  - name: joined
+    kind: date
+    start: 2020-01-01
+    end: 2020-12-31
+  - name: role
+    kind: enum
+    values: [Goalkeeper, Defender]
+  - name: uuid
+    kind: uuid
+  - name: bio
+    kind: lorem
+    words: 3
+  - name: api_key
+    kind: password
+    length: 20
+"#;
+
+    fn fake(seed: u64) -> Result<Vec<csv::StringRecord>> {
+        let schema: FakeSchema = serde_yaml::from_str(SCHEMA)?;
+        let mut buf = Vec::new();
+        process_csv_fake(&schema, 20, Some(seed), &mut buf)?;
+        Ok(ReaderBuilder::new()
+            .from_reader(buf.as_slice())
+            .records()
+            .collect::<Result<Vec<_>, _>>()?)
+    }
+
+    #[test]
+    fn test_fake_is_seedable() -> Result<()> {
+        let rows = fake(7)?;
+        assert_eq!(rows.len(), 20);
+        assert_eq!(rows, fake(7)?);
+        assert_ne!(rows, fake(8)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fake_values() -> Result<()> {
+        for (i, row) in fake(1)?.iter().enumerate() {
+            assert_eq!(row[0], (i + 1).to_string());
+            assert!(row[2].contains('@'));
+            assert!((18..=40).contains(&row[3].parse::<i64>()?));
+            assert!(row[4].starts_with("2020-"));
+            assert!(["Goalkeeper", "Defender"].contains(&&row[5]));
+            assert_eq!(row[6].len(), 36);
+            assert_eq!(&row[6][14..15], "4");
+            assert!(row[7].ends_with('.'));
+            assert_eq!(row[8].len(), 20);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_fake_invalid_schema() {
+        let schema: FakeSchema =
+            serde_yaml::from_str("columns: [{name: a, kind: int, min: 5, max: 1}]").unwrap();
+        assert!(process_csv_fake(&schema, 1, None, std::io::sink()).is_err());
+        assert!(serde_yaml::from_str::<FakeSchema>("columns: [{name: a, kind: phone}]").is_err());
+        let schema: FakeSchema = serde_yaml::from_str(
+            "columns: [{name: id, kind: sequence, start: 9223372036854775806}]",
+        )
+        .unwrap();
+        assert!(process_csv_fake(&schema, 2, None, std::io::sink()).is_ok());
+        assert!(process_csv_fake(&schema, 3, None, std::io::sink()).is_err());
+        for format in ["%Q", "%Y-%m-%d %H:%M"] {
+            let yaml = format!(
+                "columns: [{{name: a, kind: date, start: 2020-01-01, end: 2020-12-31, format: '{}'}}]",
+                format
+            );
+            let schema: FakeSchema = serde_yaml::from_str(&yaml).unwrap();
+            assert!(process_csv_fake(&schema, 1, None, std::io::sink()).is_err());
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{column_indices, format_number, FillRule, FillStrategy, RecordTransform, Records};
+use anyhow::Result;
+use csv::StringRecord;
+
+pub struct Filler {
+    rules: Vec<FillRule>,
+    missing: Vec<String>,
+}
+
+impl Filler {
+    // Cells equal to one of the missing markers are filled, the first matching rule wins
+    pub fn new(rules: Vec<FillRule>, missing: Vec<String>) -> Self {
+        Self { rules, missing }
+    }
+}
+
+impl RecordTransform for Filler {
+    fn transform<'a>(
+        self: Box<Self>,
+        headers: StringRecord,
+        records: Records<'a>,
+    ) -> Result<(StringRecord, Records<'a>)> {
+        let columns = self
+            .rules
+            .iter()
+            .map(|rule| rule.column.clone())
+            .collect::<Vec<_>>();
+        let indices = column_indices(&headers, &columns)?;
+        let needs_stats = self
+            .rules
+            .iter()
+            .any(|rule| matches!(rule.strategy, FillStrategy::Mean | FillStrategy::Median));
+
+        // mean and median need every value up front, so those rules buffer the records
+        let (records, stats): (Records, Vec<Option<String>>) = if needs_stats {
+            let buffered = records.collect::<Result<Vec<_>>>()?;
+            let stats = self
+                .rules
+                .iter()
+                .zip(indices.iter())
+                .map(|(rule, &i)| column_stat(&buffered, i, rule, &self.missing))
+                .collect::<Result<Vec<_>>>()?;
+            (Box::new(buffered.into_iter().map(Ok)), stats)
+        } else {
+            (records, vec![None; self.rules.len()])
+        };
+
+        let mut last: Vec<Option<String>> = vec![None; self.rules.len()];
+        let records = records.map(move |record| {
+            let record = record?;
+            let mut fields = record.iter().map(String::from).collect::<Vec<_>>();
+            let mut filled = vec![false; fields.len()];
+            for (n, (rule, &i)) in self.rules.iter().zip(indices.iter()).enumerate() {
+                let Some(field) = fields.get_mut(i) else {
+                    continue;
+                };
+                if !self.missing.contains(field) {
+                    if matches!(rule.strategy, FillStrategy::Down) {
+                        last[n] = Some(field.clone());
+                    }
+                    continue;
+                }
+                if filled[i] {
+                    continue;
+                }
+                let value = match &rule.strategy {
+                    FillStrategy::Value(value) => Some(value),
+                    FillStrategy::Down => last[n].as_ref(),
+                    FillStrategy::Mean | FillStrategy::Median => stats[n].as_ref(),
+                };
+                if let Some(value) = value {
+                    *field = value.clone();
+                    filled[i] = true;
+                }
+            }
+            Ok(StringRecord::from(fields))
+        });
+        Ok((headers, Box::new(records)))
+    }
+}
+
+fn column_stat(
+    records: &[StringRecord],
+    i: usize,
+    rule: &FillRule,
+    missing: &[String],
+) -> Result<Option<String>> {
+    if !matches!(rule.strategy, FillStrategy::Mean | FillStrategy::Median) {
+        return Ok(None);
+    }
+    let mut numbers = records
+        .iter()
+        .filter_map(|record| record.get(i))
+        .filter(|v| !missing.iter().any(|m| m == v))
+        .map(|v| {
+            v.trim().parse::<f64>().map_err(|_| {
+                anyhow::anyhow!(
+                    "cannot fill {} with the {} of non-numeric value {:?}",
+                    rule.column,
+                    rule.strategy,
+                    v
+                )
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if numbers.is_empty() {
+        return Ok(None);
+    }
+    let stat = match rule.strategy {
+        FillStrategy::Mean => numbers.iter().sum::<f64>() / numbers.len() as f64,
+        _ => {
+            numbers.sort_by(f64::total_cmp);
+            let mid = numbers.len() / 2;
+            if numbers.len() % 2 == 0 {
+                (numbers[mid - 1] + numbers[mid]) / 2.0
+            } else {
+                numbers[mid]
+            }
+        }
+    };
+    Ok(Some(format_number(stat)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(rules: Vec<FillRule>, rows: Vec<Vec<&str>>) -> Result<Vec<StringRecord>> {
+        let headers = StringRecord::from(vec!["team", "goals"]);
+        let records: Records = Box::new(rows.into_iter().map(|r| Ok(StringRecord::from(r))));
+        let missing = vec!["".to_string(), "NA".to_string()];
+        let filler = Box::new(Filler::new(rules, missing));
+        let (_, records) = filler.transform(headers, records)?;
+        records.collect()
+    }
+
+    fn rule(column: &str, strategy: FillStrategy) -> FillRule {
+        FillRule {
+            column: column.into(),
+            strategy,
+        }
+    }
+
+    #[test]
+    fn test_fill_down_and_value() -> Result<()> {
+        let rules = vec![
+            rule("team", FillStrategy::Down),
+            rule("team", FillStrategy::Value("unknown".into())),
+            rule("goals", FillStrategy::Value("0".into())),
+        ];
+        let ret = fill(
+            rules,
+            vec![vec!["", "NA"], vec!["Juventus", "3"], vec!["NA", ""]],
+        )?;
+        assert_eq!(ret[0], StringRecord::from(vec!["unknown", "0"]));
+        assert_eq!(ret[1], StringRecord::from(vec!["Juventus", "3"]));
+        assert_eq!(ret[2], StringRecord::from(vec!["Juventus", "0"]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill_mean_and_median() -> Result<()> {
+        let rows = vec![
+            vec!["a", "1"],
+            vec!["b", "NA"],
+            vec!["c", "2"],
+            vec!["d", "6"],
+        ];
+        let ret = fill(vec![rule("goals", FillStrategy::Mean)], rows.clone())?;
+        assert_eq!(&ret[1][1], "3");
+        let ret = fill(vec![rule("goals", FillStrategy::Median)], rows)?;
+        assert_eq!(&ret[1][1], "2");
+        let rows = vec![vec!["a", "x"], vec!["b", ""]];
+        assert!(fill(vec![rule("goals", FillStrategy::Mean)], rows).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{get_reader, Records, RowSlice};
+use anyhow::Result;
+use csv::{ReaderBuilder, StringRecord};
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::warn;
+
+const INDEX_MAGIC: &[u8; 8] = b"RCLIIDX\x01";
+// magic, csv size, mtime secs, mtime nanos, delimiter + padding, record count
+const HEADER_LEN: u64 = 8 + 8 + 8 + 4 + 4 + 8;
+
+// Sidecar index of record byte offsets, stored next to the csv as <file>.idx
+pub struct CsvIndex {
+    file: File,
+    len: usize,
+}
+
+#[derive(Debug, PartialEq)]
+struct IndexHeader {
+    size: u64,
+    secs: u64,
+    nanos: u32,
+    delimiter: u8,
+}
+
+impl IndexHeader {
+    fn for_csv(input: &Path, delimiter: u8) -> Result<Self> {
+        let meta = fs::metadata(input)?;
+        let mtime = meta.modified()?.duration_since(UNIX_EPOCH)?;
+        Ok(Self {
+            size: meta.len(),
+            secs: mtime.as_secs(),
+            nanos: mtime.subsec_nanos(),
+            delimiter,
+        })
+    }
+
+    fn to_bytes(&self, count: u64) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(HEADER_LEN as usize);
+        buf.extend_from_slice(INDEX_MAGIC);
+        buf.extend_from_slice(&self.size.to_le_bytes());
+        buf.extend_from_slice(&self.secs.to_le_bytes());
+        buf.extend_from_slice(&self.nanos.to_le_bytes());
+        buf.extend_from_slice(&[self.delimiter, 0, 0, 0]);
+        buf.extend_from_slice(&count.to_le_bytes());
+        buf
+    }
+
+    fn from_bytes(buf: &[u8; HEADER_LEN as usize]) -> Result<(Self, u64)> {
+        if &buf[..8] != INDEX_MAGIC {
+            anyhow::bail!("not an rcli csv index");
+        }
+        let u64_at = |i: usize| u64::from_le_bytes(buf[i..i + 8].try_into().unwrap());
+        let header = Self {
+            size: u64_at(8),
+            secs: u64_at(16),
+            nanos: u32::from_le_bytes(buf[24..28].try_into()?),
+            delimiter: buf[28],
+        };
+        Ok((header, u64_at(32)))
+    }
+}
+
+pub fn index_path(input: &str) -> PathBuf {
+    PathBuf::from(format!("{}.idx", input))
+}
+
+impl CsvIndex {
+    // Returns None when there is no index, or when the csv changed since it was built
+    pub fn open(input: &str, delimiter: u8) -> Result<Option<Self>> {
+        let path = index_path(input);
+        if input == "-" || !path.exists() {
+            return Ok(None);
+        }
+        let mut file = File::open(&path)?;
+        let mut buf = [0u8; HEADER_LEN as usize];
+        file.read_exact(&mut buf)?;
+        let (header, count) = IndexHeader::from_bytes(&buf)?;
+        if header != IndexHeader::for_csv(Path::new(input), delimiter)? {
+            warn!("Ignoring stale index {}", path.display());
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            file,
+            len: count as usize,
+        }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Byte offset of the i-th record, or of the end of the file when i == len
+    pub fn offset(&mut self, i: usize) -> Result<u64> {
+        if i > self.len {
+            anyhow::bail!("record {} is out of range, the file has {}", i, self.len);
+        }
+        self.file.seek(SeekFrom::Start(HEADER_LEN + i as u64 * 8))?;
+        let mut buf = [0u8; 8];
+        self.file.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+}
+
+pub fn process_csv_index(input: &str, delimiter: u8) -> Result<(PathBuf, usize)> {
+    let header = IndexHeader::for_csv(Path::new(input), delimiter)?;
+    let mut reader = ReaderBuilder::new().delimiter(delimiter).from_path(input)?;
+    reader.headers()?;
+    let path = index_path(input);
+    let mut writer = BufWriter::new(File::create(&path)?);
+    writer.write_all(&header.to_bytes(0))?;
+    let mut record = StringRecord::new();
+    let mut count = 0u64;
+    loop {
+        let pos = reader.position().byte();
+        if !reader.read_record(&mut record)? {
+            // the trailing offset marks where the last record ends
+            writer.write_all(&pos.to_le_bytes())?;
+            break;
+        }
+        writer.write_all(&pos.to_le_bytes())?;
+        count += 1;
+    }
+    writer.seek(SeekFrom::Start(0))?;
+    writer.write_all(&header.to_bytes(count))?;
+    writer.flush()?;
+    Ok((path, count as usize))
+}
+
+pub fn process_csv_count(input: &str, delimiter: u8) -> Result<usize> {
+    if let Some(index) = CsvIndex::open(input, delimiter)? {
+        return Ok(index.len());
+    }
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(get_reader(input)?);
+    let mut record = csv::ByteRecord::new();
+    let mut count = 0;
+    while reader.read_byte_record(&mut record)? {
+        count += 1;
+    }
+    Ok(count)
+}
+
+// Read only the records in the slice, seeking straight to them when an index is present
+pub fn read_csv_slice(
+    input: &str,
+    delimiter: u8,
+    slice: RowSlice,
+) -> Result<(StringRecord, Records<'static>)> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(get_reader(input)?);
+    let headers = reader.headers()?.clone();
+    let take = slice
+        .end
+        .map_or(usize::MAX, |end| end.saturating_sub(slice.start));
+    match CsvIndex::open(input, delimiter)? {
+        Some(mut index) => {
+            let start = slice.start.min(index.len());
+            let mut file = File::open(input)?;
+            file.seek(SeekFrom::Start(index.offset(start)?))?;
+            let reader = ReaderBuilder::new()
+                .delimiter(delimiter)
+                .has_headers(false)
+                .from_reader(file);
+            let records = reader.into_records().take(take).map(|r| Ok(r?));
+            Ok((headers, Box::new(records)))
+        }
+        None => {
+            let records = reader
+                .into_records()
+                .skip(slice.start)
+                .take(take)
+                .map(|r| Ok(r?));
+            Ok((headers, Box::new(records)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> Result<String> {
+        let dir = std::env::temp_dir().join(format!("rcli-index-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(name);
+        fs::copy("assets/juventus.csv", &path)?;
+        Ok(path.to_string_lossy().to_string())
+    }
+
+    fn names(input: &str, slice: RowSlice) -> Result<Vec<String>> {
+        let (_, records) = read_csv_slice(input, b',', slice)?;
+        records.map(|r| Ok(r?[0].to_string())).collect()
+    }
+
+    #[test]
+    fn test_index_count_and_slice() -> Result<()> {
+        let input = fixture("slice.csv")?;
+        let unindexed = names(&input, RowSlice::new(2, Some(4)))?;
+        let scanned = process_csv_count(&input, b',')?;
+        let (_, count) = process_csv_index(&input, b',')?;
+        assert_eq!(count, scanned);
+        assert_eq!(process_csv_count(&input, b',')?, count);
+        assert_eq!(names(&input, RowSlice::new(2, Some(4)))?, unindexed);
+        assert_eq!(unindexed, vec!["Gianluigi Buffon", "Carlo Pinsoglio"]);
+        assert_eq!(names(&input, RowSlice::new(count - 1, None))?.len(), 1);
+        assert!(names(&input, RowSlice::new(count + 5, None))?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_stale_index_is_ignored() -> Result<()> {
+        let input = fixture("stale.csv")?;
+        process_csv_index(&input, b',')?;
+        assert!(CsvIndex::open(&input, b',')?.is_some());
+        assert!(CsvIndex::open(&input, b';')?.is_none());
+        let mut file = fs::OpenOptions::new().append(true).open(&input)?;
+        writeln!(file, "Maurizio Sarri,Coach,\"Jan 10, 1959 (60)\",Italy,0")?;
+        assert!(CsvIndex::open(&input, b',')?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{column_indices, parse_date, MaskRule, MaskStrategy, RecordTransform, Records};
+use anyhow::Result;
+use csv::StringRecord;
+
+const REDACTED: &str = "REDACTED";
+
+pub struct Masker {
+    rules: Vec<MaskRule>,
+    key: Option<[u8; 32]>,
+}
+
+impl Masker {
+    pub fn try_new(rules: Vec<MaskRule>, key: Option<&[u8]>) -> Result<Self> {
+        let key = match key {
+            Some(key) if key.len() < 32 => anyhow::bail!("mask key must be at least 32 bytes"),
+            Some(key) => Some(key[..32].try_into()?),
+            None => None,
+        };
+        let needs_key = rules
+            .iter()
+            .any(|rule| matches!(rule.strategy, MaskStrategy::Pseudonym(_)));
+        if needs_key && key.is_none() {
+            anyhow::bail!("pseudonym masking requires a key, pass one with --mask-key");
+        }
+        Ok(Self { rules, key })
+    }
+
+    fn mask(&self, value: &str, strategy: MaskStrategy) -> Result<String> {
+        if value.is_empty() {
+            return Ok(String::new());
+        }
+        let ret = match strategy {
+            MaskStrategy::Redact => REDACTED.to_string(),
+            MaskStrategy::Partial(keep) => {
+                let len = value.chars().count();
+                value
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| if i + keep < len { '*' } else { c })
+                    .collect()
+            }
+            MaskStrategy::Pseudonym(len) => {
+                let key = self.key.as_ref().expect("key is checked in try_new");
+                blake3::keyed_hash(key, value.as_bytes()).to_hex()[..len].to_string()
+            }
+            MaskStrategy::Hash(len) => blake3::hash(value.as_bytes()).to_hex()[..len].to_string(),
+            MaskStrategy::Year | MaskStrategy::Month => {
+                let date = parse_date(value)
+                    .ok_or_else(|| anyhow::anyhow!("cannot generalize {:?} as a date", value))?;
+                match strategy {
+                    MaskStrategy::Year => date.format("%Y").to_string(),
+                    _ => date.format("%Y-%m").to_string(),
+                }
+            }
+            MaskStrategy::Number(width) => {
+                let number: f64 = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("cannot generalize {:?} as a number", value))?;
+                if !number.is_finite() {
+                    anyhow::bail!("cannot generalize {:?} as a number", value)
+                }
+                let bucket = (number / width as f64).floor();
+                // i64::MAX as f64 rounds up to 2^63, so it is out of range too
+                let in_range = bucket >= i64::MIN as f64 && bucket < i64::MAX as f64;
+                let bounds = i64::try_from(width)
+                    .ok()
+                    .filter(|_| in_range)
+                    .and_then(|width| {
+                        let low = (bucket as i64).checked_mul(width)?;
+                        Some((low, low.checked_add(width - 1)?))
+                    });
+                let Some((low, high)) = bounds else {
+                    anyhow::bail!("{:?} is out of range for number:{} buckets", value, width)
+                };
+                format!("{}-{}", low, high)
+            }
+        };
+        Ok(ret)
+    }
+}
+
+impl RecordTransform for Masker {
+    fn transform<'a>(
+        self: Box<Self>,
+        headers: StringRecord,
+        records: Records<'a>,
+    ) -> Result<(StringRecord, Records<'a>)> {
+        let columns = self
+            .rules
+            .iter()
+            .map(|rule| rule.column.clone())
+            .collect::<Vec<_>>();
+        let indices = column_indices(&headers, &columns)?;
+        let records = records.map(move |record| {
+            let record = record?;
+            let mut fields = record.iter().map(String::from).collect::<Vec<_>>();
+            for (rule, &i) in self.rules.iter().zip(indices.iter()) {
+                if let Some(field) = fields.get_mut(i) {
+                    *field = self.mask(field, rule.strategy)?;
+                }
+            }
+            Ok(StringRecord::from(fields))
+        });
+        Ok((headers, Box::new(records)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = include_bytes!("../../fixtures/blake3_key.txt");
+
+    fn mask(strategy: MaskStrategy, value: &str) -> Result<String> {
+        Masker::try_new(vec![], Some(KEY))?.mask(value, strategy)
+    }
+
+    #[test]
+    fn test_mask_strategies() -> Result<()> {
+        assert_eq!(mask(MaskStrategy::Redact, "Buffon")?, "REDACTED");
+        assert_eq!(mask(MaskStrategy::Partial(2), "Buffon")?, "****on");
+        assert_eq!(mask(MaskStrategy::Partial(8), "Buffon")?, "Buffon");
+        assert_eq!(mask(MaskStrategy::Hash(8), "Buffon")?.len(), 8);
+        assert_eq!(mask(MaskStrategy::Year, "Jan 28, 1978 (41)")?, "1978");
+        assert_eq!(mask(MaskStrategy::Month, "1978-01-28")?, "1978-01");
+        assert_eq!(mask(MaskStrategy::Number(10), "37")?, "30-39");
+        assert_eq!(mask(MaskStrategy::Redact, "")?, "");
+        assert!(mask(MaskStrategy::Number(10), "n/a").is_err());
+        assert_eq!(mask(MaskStrategy::Number(10), "-3")?, "-10--1");
+        for out_of_range in ["9223372036854775807", "1e30", "inf", "-inf", "NaN"] {
+            assert!(mask(MaskStrategy::Number(10), out_of_range).is_err());
+            assert!(mask(MaskStrategy::Number(1), out_of_range).is_err());
+        }
+        assert!(mask(MaskStrategy::Number(u64::MAX), "5").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_pseudonym_is_stable_and_keyed() -> Result<()> {
+        let first = mask(MaskStrategy::Pseudonym(16), "Buffon")?;
+        assert_eq!(first, mask(MaskStrategy::Pseudonym(16), "Buffon")?);
+        let other = Masker::try_new(vec![], Some(&[7u8; 32]))?;
+        assert_ne!(first, other.mask("Buffon", MaskStrategy::Pseudonym(16))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pseudonym_requires_key() {
+        let rules = vec![MaskRule {
+            column: "Name".into(),
+            strategy: MaskStrategy::Pseudonym(16),
+        }];
+        assert!(Masker::try_new(rules, None).is_err());
+    }
+}
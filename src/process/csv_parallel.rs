@@ -0,0 +1,234 @@
+use crate::{CsvIndex, Records};
+use anyhow::Result;
+use csv::{Position, ReaderBuilder, StringRecord};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde_json::Value;
+use std::fs;
+
+// Each worker gets several chunks so a slow chunk does not hold up the pool
+const CHUNKS_PER_THREAD: usize = 4;
+const VALUES_PER_CHUNK: usize = 4096;
+
+// 0 threads means one per available core
+pub fn thread_pool(threads: usize) -> Result<ThreadPool> {
+    Ok(ThreadPoolBuilder::new().num_threads(threads).build()?)
+}
+
+pub fn read_csv_parallel(
+    input: &str,
+    delimiter: u8,
+    threads: usize,
+) -> Result<(StringRecord, Records<'static>)> {
+    let data = fs::read(input)?;
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_reader(data.as_slice());
+    let headers = reader.headers()?.clone();
+    let body_start = reader.position().byte() as usize;
+
+    let pool = thread_pool(threads)?;
+    let chunks = pool.current_num_threads() * CHUNKS_PER_THREAD;
+    let bounds = match CsvIndex::open(input, delimiter)? {
+        Some(mut index) => {
+            let len = index.len();
+            let mut bounds = (0..=chunks)
+                .map(|i| index.offset(i * len / chunks).map(|pos| pos as usize))
+                .collect::<Result<Vec<_>>>()?;
+            bounds.dedup();
+            bounds
+        }
+        None => split_at_records(&data, body_start, chunks),
+    };
+
+    // chunks are parsed apart, so errors need the line each chunk starts on
+    let mut start_lines = vec![1 + count_lines(&data[..bounds[0]])];
+    for w in bounds.windows(2) {
+        let last = start_lines[start_lines.len() - 1];
+        start_lines.push(last + count_lines(&data[w[0]..w[1]]));
+    }
+    let parsed = pool.install(|| {
+        bounds
+            .par_windows(2)
+            .zip(start_lines.par_iter())
+            .map(|(w, &start_line)| {
+                parse_chunk(&data[w[0]..w[1]], delimiter, headers.len(), start_line)
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+    Ok((headers, Box::new(parsed.into_iter().flatten().map(Ok))))
+}
+
+// Records of one chunk, each checked against the header as the sequential reader does
+fn parse_chunk(
+    chunk: &[u8],
+    delimiter: u8,
+    fields: usize,
+    start_line: u64,
+) -> Result<Vec<StringRecord>> {
+    let line = |pos: Option<&Position>| start_line + pos.map_or(1, |p| p.line()) - 1;
+    let mut reader = ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(chunk);
+    let mut records = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| match e.kind() {
+            csv::ErrorKind::Utf8 { pos, err } => {
+                anyhow::anyhow!("CSV error: line {}: {}", line(pos.as_ref()), err)
+            }
+            _ => e.into(),
+        })?;
+        if record.len() != fields {
+            anyhow::bail!(
+                "CSV error: line {}: found record with {} fields, but the header has {}",
+                line(record.position()),
+                record.len(),
+                fields
+            );
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn count_lines(data: &[u8]) -> u64 {
+    data.iter().filter(|&&b| b == b'\n').count() as u64
+}
+
+// Find chunk boundaries at line ends that are outside quoted fields
+fn split_at_records(data: &[u8], start: usize, chunks: usize) -> Vec<usize> {
+    let step = (data.len().saturating_sub(start) / chunks.max(1)).max(1);
+    let mut bounds = vec![start];
+    let mut target = start + step;
+    let mut in_quotes = false;
+    for (i, &b) in data.iter().enumerate().skip(start) {
+        match b {
+            b'"' => in_quotes = !in_quotes,
+            b'\n' if !in_quotes && i + 1 >= target && i + 1 < data.len() => {
+                bounds.push(i + 1);
+                target = i + 1 + step;
+            }
+            _ => {}
+        }
+    }
+    bounds.push(data.len());
+    bounds
+}
+
+pub fn records_to_json_parallel(values: &[Value], pool: &ThreadPool) -> Result<String> {
+    let parts = pool.install(|| {
+        values
+            .par_chunks(VALUES_PER_CHUNK)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(serde_json::to_string)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(|items| items.join(","))
+            })
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    Ok(format!("[{}]", parts.join(",")))
+}
+
+pub fn records_to_yaml_parallel(values: &[Value], pool: &ThreadPool) -> Result<String> {
+    if values.is_empty() {
+        return Ok(serde_yaml::to_string(values)?);
+    }
+    // every chunk renders as "- item" lines, so the parts concatenate into one sequence
+    let parts = pool.install(|| {
+        values
+            .par_chunks(VALUES_PER_CHUNK)
+            .map(serde_yaml::to_string)
+            .collect::<Result<Vec<_>, _>>()
+    })?;
+    Ok(parts.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{process_csv_index, CsvTableReader, TableReader};
+
+    fn fixture() -> Result<String> {
+        let dir = std::env::temp_dir().join(format!("rcli-parallel-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("quoted.csv");
+        let mut content = String::from("id,note\n");
+        for i in 0..500 {
+            content.push_str(&format!("{},\"line one\nline \"\"{}\"\", two\"\n", i, i));
+        }
+        fs::write(&path, content)?;
+        Ok(path.to_string_lossy().to_string())
+    }
+
+    fn read(input: &str, threads: usize) -> Result<Vec<StringRecord>> {
+        let reader = Box::new(CsvTableReader::try_new(input, ',')?.with_threads(threads));
+        let (_, records) = reader.read()?;
+        records.collect()
+    }
+
+    #[test]
+    fn test_parallel_read_matches_sequential() -> Result<()> {
+        let input = fixture()?;
+        let sequential = read(&input, 1)?;
+        assert_eq!(sequential.len(), 500);
+        assert_eq!(read(&input, 4)?, sequential);
+        process_csv_index(&input, b',')?;
+        assert_eq!(read(&input, 3)?, sequential);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_read_checks_field_counts() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("rcli-parallel-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("short.csv");
+        // every record spans two lines, so record 399 starts on line 800
+        let mut content = String::from("id,note\n");
+        for i in 0..500 {
+            match i {
+                399 => content.push_str("399\n\n"),
+                i => content.push_str(&format!("{},\"two\nlines\"\n", i)),
+            }
+        }
+        fs::write(&path, content)?;
+        let input = path.to_string_lossy().to_string();
+        assert!(read(&input, 1).is_err());
+        let err = read(&input, 4).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "CSV error: line 800: found record with 1 fields, but the header has 2"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_at_records_skips_quoted_newlines() {
+        let data = b"a,b\n1,\"x\ny\"\n2,z\n3,w\n";
+        let bounds = split_at_records(data, 4, 3);
+        assert_eq!(bounds.first(), Some(&4));
+        assert_eq!(bounds.last(), Some(&data.len()));
+        assert!(!bounds.contains(&9));
+    }
+
+    #[test]
+    fn test_parallel_serialization_matches() -> Result<()> {
+        let pool = thread_pool(2)?;
+        let values = (0..10_000)
+            .map(|i| serde_json::json!({ "id": i.to_string(), "name": "x" }))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            records_to_json_parallel(&values, &pool)?,
+            serde_json::to_string(&values)?
+        );
+        assert_eq!(
+            records_to_yaml_parallel(&values, &pool)?,
+            serde_yaml::to_string(&values)?
+        );
+        assert_eq!(records_to_yaml_parallel(&[], &pool)?, "[]\n");
+        Ok(())
+    }
+}
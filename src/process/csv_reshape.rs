@@ -0,0 +1,294 @@
+use crate::{column_indices, format_number, Aggregation, RecordTransform, Records};
+use anyhow::Result;
+use csv::StringRecord;
+use std::collections::HashMap;
+
+pub struct Pivot {
+    index: Vec<String>,
+    columns: String,
+    values: String,
+    agg: Aggregation,
+}
+
+impl Pivot {
+    pub fn new(index: Vec<String>, columns: String, values: String, agg: Aggregation) -> Self {
+        Self {
+            index,
+            columns,
+            values,
+            agg,
+        }
+    }
+}
+
+impl RecordTransform for Pivot {
+    fn transform<'a>(
+        self: Box<Self>,
+        headers: StringRecord,
+        records: Records<'a>,
+    ) -> Result<(StringRecord, Records<'a>)> {
+        let Pivot {
+            index: index_names,
+            columns,
+            values,
+            agg,
+        } = *self;
+        let index = column_indices(&headers, &index_names)?;
+        let keys = column_indices(&headers, &[columns, values])?;
+        let (columns, values) = (keys[0], keys[1]);
+
+        // rows and new columns keep the order in which they first appear
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        let mut row_pos: HashMap<Vec<String>, usize> = HashMap::new();
+        let mut pivot_columns: Vec<String> = Vec::new();
+        let mut column_pos: HashMap<String, usize> = HashMap::new();
+        let mut cells: HashMap<(usize, usize), Accumulator> = HashMap::new();
+        for record in records {
+            let record = record?;
+            let key = index
+                .iter()
+                .map(|&i| record.get(i).unwrap_or_default().to_string())
+                .collect::<Vec<_>>();
+            let row = *row_pos.entry(key.clone()).or_insert_with(|| {
+                rows.push(key);
+                rows.len() - 1
+            });
+            let name = record.get(columns).unwrap_or_default();
+            let col = match column_pos.get(name) {
+                Some(&col) => col,
+                None => {
+                    pivot_columns.push(name.to_string());
+                    column_pos.insert(name.to_string(), pivot_columns.len() - 1);
+                    pivot_columns.len() - 1
+                }
+            };
+            let value = record.get(values).unwrap_or_default();
+            cells.entry((row, col)).or_default().push(value, agg)?;
+        }
+
+        let headers = index_names
+            .iter()
+            .chain(pivot_columns.iter())
+            .collect::<StringRecord>();
+        let width = pivot_columns.len();
+        let records = rows.into_iter().enumerate().map(move |(row, mut fields)| {
+            for col in 0..width {
+                let cell = cells.get(&(row, col));
+                fields.push(cell.map(|c| c.finish(agg)).unwrap_or_default());
+            }
+            Ok(StringRecord::from(fields))
+        });
+        Ok((headers, Box::new(records)))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Accumulator {
+    count: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    first: Option<String>,
+    last: Option<String>,
+}
+
+impl Accumulator {
+    fn push(&mut self, value: &str, agg: Aggregation) -> Result<()> {
+        if value.is_empty() {
+            return Ok(());
+        }
+        self.count += 1;
+        match agg {
+            Aggregation::Count => {}
+            Aggregation::First => {
+                self.first.get_or_insert_with(|| value.to_string());
+            }
+            Aggregation::Last => self.last = Some(value.to_string()),
+            _ => {
+                let number: f64 = value.trim().parse().map_err(|_| {
+                    anyhow::anyhow!(
+                        "cannot aggregate non-numeric value {:?} with {}",
+                        value,
+                        agg
+                    )
+                })?;
+                self.sum += number;
+                self.min = Some(self.min.map_or(number, |min| min.min(number)));
+                self.max = Some(self.max.map_or(number, |max| max.max(number)));
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&self, agg: Aggregation) -> String {
+        match agg {
+            Aggregation::Sum => format_number(self.sum),
+            Aggregation::Mean if self.count > 0 => format_number(self.sum / self.count as f64),
+            Aggregation::Count => self.count.to_string(),
+            Aggregation::Min => self.min.map(format_number).unwrap_or_default(),
+            Aggregation::Max => self.max.map(format_number).unwrap_or_default(),
+            Aggregation::First => self.first.clone().unwrap_or_default(),
+            Aggregation::Last => self.last.clone().unwrap_or_default(),
+            Aggregation::Mean => String::new(),
+        }
+    }
+}
+
+pub struct Melt {
+    id: Vec<String>,
+    value_vars: Vec<String>,
+    var_name: String,
+    value_name: String,
+}
+
+impl Melt {
+    // An empty value_vars melts every column that is not an id column
+    pub fn new(
+        id: Vec<String>,
+        value_vars: Vec<String>,
+        var_name: String,
+        value_name: String,
+    ) -> Self {
+        Self {
+            id,
+            value_vars,
+            var_name,
+            value_name,
+        }
+    }
+}
+
+impl RecordTransform for Melt {
+    fn transform<'a>(
+        self: Box<Self>,
+        headers: StringRecord,
+        records: Records<'a>,
+    ) -> Result<(StringRecord, Records<'a>)> {
+        let id = column_indices(&headers, &self.id)?;
+        let value_vars = if self.value_vars.is_empty() {
+            (0..headers.len()).filter(|i| !id.contains(i)).collect()
+        } else {
+            column_indices(&headers, &self.value_vars)?
+        };
+        let names = value_vars
+            .iter()
+            .map(|&i| headers[i].to_string())
+            .collect::<Vec<_>>();
+        let new_headers = self
+            .id
+            .iter()
+            .chain([&self.var_name, &self.value_name])
+            .collect::<StringRecord>();
+        let records = records.flat_map(move |record| {
+            let ret: Vec<Result<StringRecord>> = match record {
+                Ok(record) => value_vars
+                    .iter()
+                    .zip(names.iter())
+                    .map(|(&i, name)| {
+                        let mut fields = id
+                            .iter()
+                            .map(|&j| record.get(j).unwrap_or_default())
+                            .collect::<StringRecord>();
+                        fields.push_field(name);
+                        fields.push_field(record.get(i).unwrap_or_default());
+                        Ok(fields)
+                    })
+                    .collect(),
+                Err(e) => vec![Err(e)],
+            };
+            ret
+        });
+        Ok((new_headers, Box::new(records)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(rows: Vec<Vec<&'static str>>) -> (StringRecord, Records<'static>) {
+        let mut rows = rows.into_iter().map(StringRecord::from);
+        let headers = rows.next().unwrap();
+        (headers, Box::new(rows.map(Ok)))
+    }
+
+    fn collect((headers, records): (StringRecord, Records)) -> Result<Vec<StringRecord>> {
+        std::iter::once(Ok(headers)).chain(records).collect()
+    }
+
+    fn goals() -> (StringRecord, Records<'static>) {
+        table(vec![
+            vec!["player", "season", "goals"],
+            vec!["Dybala", "2019", "11"],
+            vec!["Ronaldo", "2019", "31"],
+            vec!["Dybala", "2020", "5"],
+            vec!["Dybala", "2020", "2"],
+        ])
+    }
+
+    #[test]
+    fn test_pivot_sum() -> Result<()> {
+        let (headers, records) = goals();
+        let pivot = Box::new(Pivot::new(
+            vec!["player".into()],
+            "season".into(),
+            "goals".into(),
+            Aggregation::Sum,
+        ));
+        let ret = collect(pivot.transform(headers, records)?)?;
+        assert_eq!(ret[0], StringRecord::from(vec!["player", "2019", "2020"]));
+        assert_eq!(ret[1], StringRecord::from(vec!["Dybala", "11", "7"]));
+        assert_eq!(ret[2], StringRecord::from(vec!["Ronaldo", "31", ""]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pivot_mean_and_count() -> Result<()> {
+        for (agg, expected) in [(Aggregation::Mean, "3.5"), (Aggregation::Count, "2")] {
+            let (headers, records) = goals();
+            let pivot = Box::new(Pivot::new(
+                vec!["player".into()],
+                "season".into(),
+                "goals".into(),
+                agg,
+            ));
+            let ret = collect(pivot.transform(headers, records)?)?;
+            assert_eq!(&ret[1][2], expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pivot_non_numeric() {
+        let (headers, records) = goals();
+        let pivot = Box::new(Pivot::new(
+            vec!["season".into()],
+            "goals".into(),
+            "player".into(),
+            Aggregation::Sum,
+        ));
+        assert!(pivot.transform(headers, records).and_then(collect).is_err());
+    }
+
+    #[test]
+    fn test_melt() -> Result<()> {
+        let (headers, records) = table(vec![
+            vec!["player", "2019", "2020"],
+            vec!["Dybala", "11", "7"],
+        ]);
+        let melt = Box::new(Melt::new(
+            vec!["player".into()],
+            vec![],
+            "season".into(),
+            "goals".into(),
+        ));
+        let ret = collect(melt.transform(headers, records)?)?;
+        assert_eq!(
+            ret[0],
+            StringRecord::from(vec!["player", "season", "goals"])
+        );
+        assert_eq!(ret[1], StringRecord::from(vec!["Dybala", "2019", "11"]));
+        assert_eq!(ret[2], StringRecord::from(vec!["Dybala", "2020", "7"]));
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+use crate::{column_indices, DedupKeep, RecordTransform, Records};
+use anyhow::Result;
+use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, HashSet};
+use std::io::{Seek, SeekFrom};
+
+#[derive(Debug, Clone, Copy)]
+pub enum SampleSize {
+    Count(usize),
+    Fraction(f64),
+}
+
+pub struct Sampler {
+    size: SampleSize,
+    rng: StdRng,
+}
+
+impl Sampler {
+    pub fn new(size: SampleSize, seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self { size, rng }
+    }
+}
+
+impl RecordTransform for Sampler {
+    fn transform<'a>(
+        self: Box<Self>,
+        headers: StringRecord,
+        records: Records<'a>,
+    ) -> Result<(StringRecord, Records<'a>)> {
+        let mut rng = self.rng;
+        let records: Records = match self.size {
+            SampleSize::Count(n) => {
+                let sample = reservoir_sample(records, n, &mut rng)?;
+                Box::new(sample.into_iter().map(Ok))
+            }
+            SampleSize::Fraction(fraction) => {
+                Box::new(records.filter(move |r| r.is_err() || rng.gen_bool(fraction)))
+            }
+        };
+        Ok((headers, records))
+    }
+}
+
+// Algorithm R: keep n items in memory, then restore the input order
+fn reservoir_sample<T>(
+    items: impl Iterator<Item = Result<T>>,
+    n: usize,
+    rng: &mut impl Rng,
+) -> Result<Vec<T>> {
+    let mut reservoir: Vec<(usize, T)> = Vec::with_capacity(n.min(1024));
+    for (i, item) in items.enumerate() {
+        let item = item?;
+        if i < n {
+            reservoir.push((i, item));
+        } else {
+            let j = rng.gen_range(0..=i);
+            if j < n {
+                reservoir[j] = (i, item);
+            }
+        }
+    }
+    reservoir.sort_by_key(|(i, _)| *i);
+    Ok(reservoir.into_iter().map(|(_, item)| item).collect())
+}
+
+pub struct Deduplicator {
+    columns: Vec<String>,
+    keep: DedupKeep,
+}
+
+impl Deduplicator {
+    // An empty column list compares whole rows
+    pub fn new(columns: Vec<String>, keep: DedupKeep) -> Self {
+        Self { columns, keep }
+    }
+}
+
+impl RecordTransform for Deduplicator {
+    fn transform<'a>(
+        self: Box<Self>,
+        headers: StringRecord,
+        records: Records<'a>,
+    ) -> Result<(StringRecord, Records<'a>)> {
+        let indices = if self.columns.is_empty() {
+            (0..headers.len()).collect()
+        } else {
+            column_indices(&headers, &self.columns)?
+        };
+        let records: Records = match self.keep {
+            DedupKeep::First => {
+                let mut seen = HashSet::new();
+                Box::new(records.filter(move |r| match r {
+                    Ok(record) => seen.insert(record_key(record, &indices)),
+                    Err(_) => true,
+                }))
+            }
+            DedupKeep::Last => Box::new(keep_last(records, indices)?),
+        };
+        Ok((headers, records))
+    }
+}
+
+// Spool the rows to a temporary file while noting the last row of each key, then
+// stream them back, so only a hash and an index per distinct row stay in memory
+fn keep_last<'a>(
+    records: Records<'a>,
+    indices: Vec<usize>,
+) -> Result<impl Iterator<Item = Result<StringRecord>> + 'a> {
+    let mut spool = WriterBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_writer(tempfile::tempfile()?);
+    let mut last = HashMap::new();
+    for (i, record) in records.enumerate() {
+        let record = record?;
+        last.insert(record_key(&record, &indices), i);
+        spool.write_record(&record)?;
+    }
+    let mut file = spool.into_inner().map_err(|e| e.into_error())?;
+    file.seek(SeekFrom::Start(0))?;
+    let reader = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file);
+    let ret = reader
+        .into_records()
+        .enumerate()
+        .filter_map(move |(i, record)| match record {
+            Ok(record) => {
+                (last.get(&record_key(&record, &indices)) == Some(&i)).then_some(Ok(record))
+            }
+            Err(e) => Some(Err(e.into())),
+        });
+    Ok(ret)
+}
+
+// Hash the key columns so only 32 bytes per distinct row are kept in memory
+fn record_key(record: &StringRecord, indices: &[usize]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    for &i in indices {
+        let field = record.get(i).unwrap_or_default();
+        hasher.update(&(field.len() as u64).to_le_bytes());
+        hasher.update(field.as_bytes());
+    }
+    *hasher.finalize().as_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers() -> StringRecord {
+        StringRecord::from(vec!["name", "team"])
+    }
+
+    fn records() -> Records<'static> {
+        let rows = vec![
+            vec!["a", "x"],
+            vec!["b", "y"],
+            vec!["a", "z"],
+            vec!["c", "y"],
+        ];
+        Box::new(rows.into_iter().map(|r| Ok(StringRecord::from(r))))
+    }
+
+    fn names((_, records): (StringRecord, Records)) -> Result<Vec<String>> {
+        records
+            .map(|r| {
+                let r = r?;
+                Ok(format!("{}{}", &r[0], &r[1]))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dedup_keep_first() -> Result<()> {
+        let dedup = Box::new(Deduplicator::new(vec!["name".into()], DedupKeep::First));
+        let ret = names(dedup.transform(headers(), records())?)?;
+        assert_eq!(ret, vec!["ax", "by", "cy"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedup_keep_last() -> Result<()> {
+        let dedup = Box::new(Deduplicator::new(vec!["team".into()], DedupKeep::Last));
+        let ret = names(dedup.transform(headers(), records())?)?;
+        assert_eq!(ret, vec!["ax", "az", "cy"]);
+
+        // rows of different widths and quoted fields survive the spool
+        let rows = vec![
+            vec!["a,1", "x\n"],
+            vec!["b"],
+            vec!["a,1", "x\n"],
+            vec!["", ""],
+        ];
+        let records: Records = Box::new(rows.into_iter().map(|r| Ok(StringRecord::from(r))));
+        let dedup = Box::new(Deduplicator::new(vec![], DedupKeep::Last));
+        let (_, records) = dedup.transform(headers(), records)?;
+        let ret = records.collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            ret,
+            vec![
+                StringRecord::from(vec!["b"]),
+                StringRecord::from(vec!["a,1", "x\n"]),
+                StringRecord::from(vec!["", ""]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedup_unknown_column() {
+        let dedup = Box::new(Deduplicator::new(vec!["age".into()], DedupKeep::First));
+        assert!(dedup.transform(headers(), records()).is_err());
+    }
+
+    #[test]
+    fn test_sample_is_reproducible() -> Result<()> {
+        let first = Box::new(Sampler::new(SampleSize::Count(2), Some(42)));
+        let second = Box::new(Sampler::new(SampleSize::Count(2), Some(42)));
+        let first = names(first.transform(headers(), records())?)?;
+        let second = names(second.transform(headers(), records())?)?;
+        assert_eq!(first.len(), 2);
+        assert_eq!(first, second);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_more_than_available() -> Result<()> {
+        let sampler = Box::new(Sampler::new(SampleSize::Count(10), None));
+        let ret = names(sampler.transform(headers(), records())?)?;
+        assert_eq!(ret, vec!["ax", "by", "az", "cy"]);
+        let sampler = Box::new(Sampler::new(SampleSize::Count(usize::MAX), None));
+        let ret = names(sampler.transform(headers(), records())?)?;
+        assert_eq!(ret, vec!["ax", "by", "az", "cy"]);
+        Ok(())
+    }
+}
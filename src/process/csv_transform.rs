@@ -0,0 +1,437 @@
+use crate::{parse_date, RecordTransform, Records};
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime};
+use csv::StringRecord;
+use regex::Regex;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// A rule like `Name=trim | upper` rewrites one column, creating it when it does not exist
+#[derive(Debug, Clone)]
+pub struct TransformRule {
+    pub column: String,
+    pub steps: Vec<TransformStep>,
+}
+
+#[derive(Debug, Clone)]
+pub enum TransformStep {
+    Trim,
+    Upper,
+    Lower,
+    Replace(Regex, String),
+    Extract(Regex),
+    Split(String, Vec<String>),
+    Date(Option<String>),
+    Number(usize),
+    Concat(Vec<ConcatPart>),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConcatPart {
+    Literal(String),
+    Column(String),
+}
+
+pub struct Transformer {
+    rules: Vec<TransformRule>,
+}
+
+// A step with its column names resolved to record positions
+enum Plan {
+    Step(TransformStep),
+    Split(String, Vec<usize>),
+    Concat(Vec<Part>),
+}
+
+enum Part {
+    Literal(String),
+    Column(usize),
+}
+
+impl Transformer {
+    pub fn new(rules: Vec<TransformRule>) -> Self {
+        Self { rules }
+    }
+}
+
+impl RecordTransform for Transformer {
+    fn transform<'a>(
+        self: Box<Self>,
+        headers: StringRecord,
+        records: Records<'a>,
+    ) -> Result<(StringRecord, Records<'a>)> {
+        // rules run in order, so later rules see the columns and values earlier ones produced
+        let mut names = headers.iter().map(String::from).collect::<Vec<_>>();
+        let mut position = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), i))
+            .collect::<HashMap<_, _>>();
+        let mut plans = Vec::with_capacity(self.rules.len());
+        for rule in self.rules {
+            let mut steps = Vec::with_capacity(rule.steps.len());
+            for step in rule.steps {
+                let step = match step {
+                    TransformStep::Split(sep, columns) => {
+                        let targets = columns
+                            .iter()
+                            .map(|c| add_column(c, &mut names, &mut position))
+                            .collect();
+                        Plan::Split(sep, targets)
+                    }
+                    TransformStep::Concat(parts) => {
+                        let parts = parts
+                            .into_iter()
+                            .map(|part| match part {
+                                ConcatPart::Literal(s) => Ok(Part::Literal(s)),
+                                ConcatPart::Column(name) => match position.get(&name) {
+                                    Some(&i) => Ok(Part::Column(i)),
+                                    None => anyhow::bail!("column {} not found in header", name),
+                                },
+                            })
+                            .collect::<Result<_>>()?;
+                        Plan::Concat(parts)
+                    }
+                    step => Plan::Step(step),
+                };
+                steps.push(step);
+            }
+            let target = add_column(&rule.column, &mut names, &mut position);
+            plans.push((rule.column, target, steps));
+        }
+
+        let width = names.len();
+        let records = records.enumerate().map(move |(row, record)| {
+            let record = record?;
+            let mut fields = record.iter().map(String::from).collect::<Vec<_>>();
+            fields.resize(width, String::new());
+            for (column, target, steps) in &plans {
+                let mut value = fields[*target].clone();
+                for step in steps {
+                    value = apply(step, value, &mut fields).map_err(|e| {
+                        anyhow::anyhow!("row {}: cannot transform {}: {}", row + 1, column, e)
+                    })?;
+                }
+                fields[*target] = value;
+            }
+            Ok(StringRecord::from(fields))
+        });
+        Ok((StringRecord::from(names), Box::new(records)))
+    }
+}
+
+fn add_column(name: &str, names: &mut Vec<String>, position: &mut HashMap<String, usize>) -> usize {
+    *position.entry(name.to_string()).or_insert_with(|| {
+        names.push(name.to_string());
+        names.len() - 1
+    })
+}
+
+fn apply(step: &Plan, value: String, fields: &mut [String]) -> Result<String> {
+    let step = match step {
+        Plan::Split(sep, targets) => {
+            let mut parts = value.splitn(targets.len(), sep.as_str());
+            for &i in targets {
+                fields[i] = parts.next().unwrap_or_default().to_string();
+            }
+            return Ok(value);
+        }
+        Plan::Concat(parts) => {
+            return Ok(parts
+                .iter()
+                .map(|part| match part {
+                    Part::Literal(s) => s.as_str(),
+                    Part::Column(i) => fields[*i].as_str(),
+                })
+                .collect());
+        }
+        Plan::Step(step) => step,
+    };
+    let ret = match step {
+        TransformStep::Trim => value.trim().to_string(),
+        TransformStep::Upper => value.to_uppercase(),
+        TransformStep::Lower => value.to_lowercase(),
+        TransformStep::Replace(re, with) => re.replace_all(&value, with.as_str()).into_owned(),
+        TransformStep::Extract(re) => re
+            .captures(&value)
+            .and_then(|c| c.get(1).or_else(|| c.get(0)))
+            .map(|m| m.as_str().to_string())
+            .unwrap_or_default(),
+        TransformStep::Date(_) | TransformStep::Number(_) if value.trim().is_empty() => value,
+        TransformStep::Date(format) => reformat_date(value.trim(), format.as_deref())?,
+        TransformStep::Number(decimals) => {
+            let number: f64 = value
+                .trim()
+                .parse()
+                .map_err(|_| anyhow::anyhow!("{:?} is not a number", value))?;
+            format!("{:.*}", decimals, number)
+        }
+        TransformStep::Split(..) | TransformStep::Concat(_) => {
+            unreachable!("split and concat are resolved into plans")
+        }
+    };
+    Ok(ret)
+}
+
+// Parse with the given chrono format, or guess a common one, and emit ISO-8601
+fn reformat_date(value: &str, format: Option<&str>) -> Result<String> {
+    let Some(format) = format else {
+        return parse_date(value)
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .ok_or_else(|| anyhow::anyhow!("{:?} is not a recognized date", value));
+    };
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+        return Ok(datetime.format("%Y-%m-%dT%H:%M:%S").to_string());
+    }
+    NaiveDate::parse_from_str(value, format)
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .map_err(|_| anyhow::anyhow!("{:?} does not match date format {:?}", value, format))
+}
+
+impl FromStr for TransformRule {
+    type Err = anyhow::Error;
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let (column, expr) = rule
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("transform must be col=expr"))?;
+        let steps = ExprParser::new(expr).parse_pipeline()?;
+        if let Some(i) = steps
+            .iter()
+            .position(|step| matches!(step, TransformStep::Split(..)))
+        {
+            if i + 1 != steps.len() {
+                anyhow::bail!("split must be the last step of a transform");
+            }
+        }
+        Ok(TransformRule {
+            column: column.trim().to_string(),
+            steps,
+        })
+    }
+}
+
+// An argument is a quoted literal, a [bracketed] or bare column name, or a number
+#[derive(Debug)]
+struct Arg {
+    text: String,
+    quoted: bool,
+}
+
+struct ExprParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn new(expr: &str) -> Self {
+        Self {
+            chars: expr.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+        self.chars.get(self.pos).copied()
+    }
+
+    fn parse_pipeline(&mut self) -> Result<Vec<TransformStep>> {
+        let mut steps = vec![self.parse_step()?];
+        while let Some(c) = self.peek() {
+            if c != '|' {
+                anyhow::bail!("expected '|' at position {} of transform", self.pos);
+            }
+            self.pos += 1;
+            steps.push(self.parse_step()?);
+        }
+        Ok(steps)
+    }
+
+    fn parse_step(&mut self) -> Result<TransformStep> {
+        self.peek();
+        let start = self.pos;
+        while self
+            .chars
+            .get(self.pos)
+            .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_')
+        {
+            self.pos += 1;
+        }
+        let name = self.chars[start..self.pos].iter().collect::<String>();
+        let args = if self.peek() == Some('(') {
+            self.pos += 1;
+            self.parse_args()?
+        } else {
+            vec![]
+        };
+        let literal = |arg: &Arg| -> Result<String> {
+            match arg.quoted {
+                true => Ok(arg.text.clone()),
+                false => anyhow::bail!("{} expects a quoted string, got {}", name, arg.text),
+            }
+        };
+        let step = match (name.as_str(), args.as_slice()) {
+            ("trim", []) => TransformStep::Trim,
+            ("upper", []) => TransformStep::Upper,
+            ("lower", []) => TransformStep::Lower,
+            ("replace", [re, with]) => {
+                TransformStep::Replace(Regex::new(&literal(re)?)?, literal(with)?)
+            }
+            ("extract", [re]) => TransformStep::Extract(Regex::new(&literal(re)?)?),
+            ("split", [sep, columns @ ..]) if !columns.is_empty() => TransformStep::Split(
+                literal(sep)?,
+                columns.iter().map(|c| c.text.clone()).collect(),
+            ),
+            ("date", []) => TransformStep::Date(None),
+            ("date", [format]) => TransformStep::Date(Some(literal(format)?)),
+            ("number", [decimals]) => TransformStep::Number(decimals.text.parse()?),
+            ("concat", parts) if !parts.is_empty() => TransformStep::Concat(
+                parts
+                    .iter()
+                    .map(|arg| match arg.quoted {
+                        true => ConcatPart::Literal(arg.text.clone()),
+                        false => ConcatPart::Column(arg.text.clone()),
+                    })
+                    .collect(),
+            ),
+            ("", _) => anyhow::bail!("expected a function at position {} of transform", start),
+            (name, args) => anyhow::bail!(
+                "Unsupported transform {} with {} arguments, expected one of trim, upper, lower, \
+                 replace('re', 'with'), extract('re'), split('sep', col, ...), date(['format']), \
+                 number(decimals) or concat(col|'text', ...)",
+                name,
+                args.len()
+            ),
+        };
+        Ok(step)
+    }
+
+    fn parse_args(&mut self) -> Result<Vec<Arg>> {
+        let mut args = Vec::new();
+        if self.peek() == Some(')') {
+            self.pos += 1;
+            return Ok(args);
+        }
+        loop {
+            args.push(self.parse_arg()?);
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(')') => {
+                    self.pos += 1;
+                    return Ok(args);
+                }
+                _ => anyhow::bail!("expected ',' or ')' at position {} of transform", self.pos),
+            }
+        }
+    }
+
+    fn parse_arg(&mut self) -> Result<Arg> {
+        let (close, quoted) = match self.peek() {
+            Some(q @ ('\'' | '"')) => (q, true),
+            Some('[') => (']', false),
+            Some(_) => {
+                let start = self.pos;
+                while self
+                    .chars
+                    .get(self.pos)
+                    .is_some_and(|c| !matches!(c, ',' | ')') && !c.is_whitespace())
+                {
+                    self.pos += 1;
+                }
+                let text = self.chars[start..self.pos].iter().collect();
+                return Ok(Arg {
+                    text,
+                    quoted: false,
+                });
+            }
+            None => anyhow::bail!("unterminated argument list in transform"),
+        };
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            match self.chars.get(self.pos) {
+                // a backslash escapes the closing quote and itself, other escapes reach the regex as-is
+                Some('\\') if matches!(self.chars.get(self.pos + 1), Some(&c) if c == close || c == '\\') =>
+                {
+                    text.push(self.chars[self.pos + 1]);
+                    self.pos += 2;
+                }
+                Some(&c) if c == close => {
+                    self.pos += 1;
+                    return Ok(Arg { text, quoted });
+                }
+                Some(&c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+                None => anyhow::bail!("missing closing {} in transform", close),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transform(rules: &[&str], row: Vec<&str>) -> Result<(StringRecord, StringRecord)> {
+        let rules = rules
+            .iter()
+            .map(|r| r.parse())
+            .collect::<Result<Vec<_>>>()?;
+        let headers = StringRecord::from(vec!["Name", "DOB", "Kit Number"]);
+        let records: Records = Box::new(std::iter::once(Ok(StringRecord::from(row))));
+        let (headers, mut records) =
+            Box::new(Transformer::new(rules)).transform(headers, records)?;
+        Ok((headers, records.next().unwrap()?))
+    }
+
+    #[test]
+    fn test_string_transforms() -> Result<()> {
+        let (_, ret) = transform(
+            &[
+                "Name=trim | upper | replace('\\s+', '_')",
+                "Kit Number=extract('(\\d+)') | number(1)",
+            ],
+            vec!["  Paulo  Dybala ", "", "No. 10"],
+        )?;
+        assert_eq!(ret, StringRecord::from(vec!["PAULO_DYBALA", "", "10.0"]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_concat_and_date() -> Result<()> {
+        let (headers, ret) = transform(
+            &[
+                "Name=split(' ', First, Last)",
+                "Label=concat(Last, ' #', [Kit Number])",
+                "DOB=date('%d/%m/%Y')",
+            ],
+            vec!["Paulo Dybala", "15/11/1993", "10"],
+        )?;
+        assert_eq!(
+            headers,
+            StringRecord::from(vec!["Name", "DOB", "Kit Number", "First", "Last", "Label"])
+        );
+        assert_eq!(&ret[1], "1993-11-15");
+        assert_eq!(&ret[3], "Paulo");
+        assert_eq!(&ret[5], "Dybala #10");
+        let (_, ret) = transform(&["DOB=date"], vec!["", "Nov 15, 1993 (26)", ""])?;
+        assert_eq!(&ret[1], "1993-11-15");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_transforms() {
+        assert!("Name".parse::<TransformRule>().is_err());
+        assert!("Name=shout".parse::<TransformRule>().is_err());
+        assert!("Name=replace('(', 'x')".parse::<TransformRule>().is_err());
+        assert!("Name=split(' ', a, b) | trim"
+            .parse::<TransformRule>()
+            .is_err());
+        assert!(transform(&["DOB=date('%d/%m/%Y')"], vec!["", "1993-11-15", ""]).is_err());
+        assert!(transform(&["Label=concat(Team)"], vec!["", "", ""]).is_err());
+    }
+}
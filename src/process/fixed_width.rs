@@ -0,0 +1,244 @@
+use crate::{column_indices, get_reader, Records, TableReader, TableWriter};
+use anyhow::Result;
+use csv::StringRecord;
+use serde::Deserialize;
+use std::io::{BufRead, BufReader};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixedWidthSpec {
+    pub columns: Vec<FixedWidthColumn>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FixedWidthColumn {
+    pub name: String,
+    // 1-based character position, as printed in record layouts
+    pub start: usize,
+    pub width: usize,
+    #[serde(default)]
+    pub align: Alignment,
+    #[serde(default = "default_padding")]
+    pub padding: char,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Alignment {
+    #[default]
+    Left,
+    Right,
+}
+
+fn default_padding() -> char {
+    ' '
+}
+
+impl FixedWidthSpec {
+    pub fn load(path: &str) -> Result<Self> {
+        let spec: Self = serde_yaml::from_reader(get_reader(path)?)?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.columns.is_empty() {
+            anyhow::bail!("fixed-width spec must declare at least one column");
+        }
+        if let Some(col) = self.columns.iter().find(|col| col.start == 0) {
+            anyhow::bail!("column {} starts at 0, positions are 1-based", col.name);
+        }
+        if let Some(col) = self.columns.iter().find(|col| col.width == 0) {
+            anyhow::bail!("column {} has no width", col.name);
+        }
+        // columns are listed left to right, each starting after the one before ends
+        for pair in self.columns.windows(2) {
+            let (prev, col) = (&pair[0], &pair[1]);
+            if col.start < prev.start + prev.width {
+                anyhow::bail!(
+                    "column {} starts at {}, before column {} ends at {}",
+                    col.name,
+                    col.start,
+                    prev.name,
+                    prev.start + prev.width - 1
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn headers(&self) -> StringRecord {
+        self.columns.iter().map(|col| col.name.as_str()).collect()
+    }
+
+    fn parse_line(&self, line: &str) -> StringRecord {
+        let chars = line.chars().collect::<Vec<_>>();
+        self.columns
+            .iter()
+            .map(|col| {
+                let start = (col.start - 1).min(chars.len());
+                let end = (start + col.width).min(chars.len());
+                let field = chars[start..end].iter().collect::<String>();
+                let trimmed = match col.align {
+                    Alignment::Left => field.trim_end_matches(col.padding),
+                    Alignment::Right => field.trim_start_matches(col.padding),
+                };
+                // a field of nothing but "0" padding is the value 0, not an empty one
+                if trimmed.is_empty() && !col.padding.is_whitespace() {
+                    field.chars().last().map(String::from).unwrap_or_default()
+                } else {
+                    trimmed.to_string()
+                }
+            })
+            .collect()
+    }
+}
+
+pub struct FixedWidthReader {
+    input: String,
+    spec: FixedWidthSpec,
+}
+
+impl FixedWidthReader {
+    pub fn new(input: &str, spec: FixedWidthSpec) -> Self {
+        Self {
+            input: input.to_string(),
+            spec,
+        }
+    }
+}
+
+impl TableReader for FixedWidthReader {
+    fn read(self: Box<Self>) -> Result<(StringRecord, Records<'static>)> {
+        let lines = BufReader::new(get_reader(&self.input)?).lines();
+        let headers = self.spec.headers();
+        let spec = self.spec;
+        let records = lines.filter_map(move |line| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(Ok(spec.parse_line(line.trim_end_matches('\r')))),
+            Err(e) => Some(Err(e.into())),
+        });
+        Ok((headers, Box::new(records)))
+    }
+}
+
+pub struct FixedWidthWriter {
+    spec: FixedWidthSpec,
+}
+
+impl FixedWidthWriter {
+    pub fn new(spec: FixedWidthSpec) -> Self {
+        Self { spec }
+    }
+}
+
+impl TableWriter for FixedWidthWriter {
+    fn write(&self, headers: &StringRecord, records: Records) -> Result<String> {
+        let names = self
+            .spec
+            .columns
+            .iter()
+            .map(|col| col.name.clone())
+            .collect::<Vec<_>>();
+        let indices = column_indices(headers, &names)?;
+        let line_len = self
+            .spec
+            .columns
+            .iter()
+            .map(|col| col.start - 1 + col.width)
+            .max()
+            .unwrap_or_default();
+        let mut content = String::new();
+        for (row, record) in records.enumerate() {
+            let record = record?;
+            let mut line = vec![' '; line_len];
+            for (col, &i) in self.spec.columns.iter().zip(indices.iter()) {
+                let value = record.get(i).unwrap_or_default();
+                let len = value.chars().count();
+                if len > col.width {
+                    anyhow::bail!(
+                        "row {}: value {:?} in column {} is {} characters, wider than {}",
+                        row + 1,
+                        value,
+                        col.name,
+                        len,
+                        col.width
+                    );
+                }
+                let pad = std::iter::repeat_n(col.padding, col.width - len);
+                let field = match col.align {
+                    Alignment::Left => value.chars().chain(pad).collect::<Vec<_>>(),
+                    Alignment::Right => pad.chain(value.chars()).collect::<Vec<_>>(),
+                };
+                let start = col.start - 1;
+                line[start..start + col.width].copy_from_slice(&field);
+            }
+            content.extend(line);
+            content.push('\n');
+        }
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> FixedWidthSpec {
+        let yaml = r#"
+columns:
+  - { name: Name, start: 1, width: 10 }
+  - { name: Kit, start: 11, width: 4, align: right, padding: "0" }
+"#;
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let record = spec().parse_line("Buffon    0077");
+        assert_eq!(record, StringRecord::from(vec!["Buffon", "77"]));
+        let record = spec().parse_line("Dybala");
+        assert_eq!(record, StringRecord::from(vec!["Dybala", ""]));
+        let record = spec().parse_line("          0000");
+        assert_eq!(record, StringRecord::from(vec!["", "0"]));
+    }
+
+    #[test]
+    fn test_spec_validation() {
+        assert!(spec().validate().is_ok());
+        for columns in [
+            "[]",
+            "[{ name: a, start: 0, width: 2 }]",
+            "[{ name: a, start: 1, width: 0 }]",
+            "[{ name: a, start: 1, width: 4 }, { name: b, start: 4, width: 2 }]",
+            "[{ name: a, start: 5, width: 2 }, { name: b, start: 1, width: 2 }]",
+        ] {
+            let spec: FixedWidthSpec =
+                serde_yaml::from_str(&format!("columns: {}", columns)).unwrap();
+            assert!(spec.validate().is_err(), "{}", columns);
+        }
+    }
+
+    #[test]
+    fn test_write_round_trip() -> Result<()> {
+        let headers = StringRecord::from(vec!["Kit", "Name"]);
+        let records: Records = Box::new(
+            vec![vec!["77", "Buffon"], vec!["10", "Dybala"]]
+                .into_iter()
+                .map(|r| Ok(StringRecord::from(r))),
+        );
+        let content = FixedWidthWriter::new(spec()).write(&headers, records)?;
+        assert_eq!(content, "Buffon    0077\nDybala    0010\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_truncation_error() {
+        let headers = StringRecord::from(vec!["Name", "Kit"]);
+        let records: Records = Box::new(std::iter::once(Ok(StringRecord::from(vec![
+            "Gianluigi Buffon",
+            "77",
+        ]))));
+        let ret = FixedWidthWriter::new(spec()).write(&headers, records);
+        assert!(ret.is_err());
+    }
+}
@@ -1,50 +1,1293 @@
+use crate::{get_content, PasswordFormat, PasswordPattern, WordCase};
 use anyhow::Result;
+use num_bigint::BigUint;
 use rand::seq::SliceRandom;
-const UPPER: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
-const LOWER: &[u8] = b"abcdefghijkmnopqrstuvwxyz";
-const NUMBER: &[u8] = b"123456789";
-const SYMBOL: &[u8] = b"!@#$%^&*_";
-pub fn process_genpass(
-    length: u8,
-    upper: bool,
-    lower: bool,
-    number: bool,
-    symbol: bool,
+use rand::Rng;
+use serde::Serialize;
+use std::collections::HashSet;
+
+// The longest password, pattern output included, that will be generated
+pub const MAX_PASSWORD_LENGTH: usize = 4096;
+const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+const NUMBER: &str = "0123456789";
+const SYMBOL: &str = "!@#$%^&*_";
+// Look-alike characters left out unless allow_ambiguous is set
+pub const AMBIGUOUS: &str = "0OIl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Upper,
+    Lower,
+    Number,
+    Symbol,
+}
+
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub length: usize,
+    pub upper: bool,
+    pub lower: bool,
+    pub number: bool,
+    pub symbol: bool,
+    // a custom alphabet replaces the four classes, and is used as given apart from exclude
+    pub charset: Option<String>,
+    pub symbols: Option<String>,
+    pub exclude: String,
+    pub allow_ambiguous: bool,
+    // without a minimum, every enabled class appears at least once (custom alphabets excepted)
+    pub min_upper: Option<usize>,
+    pub min_lower: Option<usize>,
+    pub min_number: Option<usize>,
+    pub min_symbol: Option<usize>,
+    pub max_upper: Option<usize>,
+    pub max_lower: Option<usize>,
+    pub max_number: Option<usize>,
+    pub max_symbol: Option<usize>,
+    // no two equal neighbours (aa) and no runs of three (abc, 321)
+    pub no_repeat: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            length: 16,
+            upper: true,
+            lower: true,
+            number: true,
+            symbol: true,
+            charset: None,
+            symbols: None,
+            exclude: String::new(),
+            allow_ambiguous: false,
+            min_upper: None,
+            min_lower: None,
+            min_number: None,
+            min_symbol: None,
+            max_upper: None,
+            max_lower: None,
+            max_number: None,
+            max_symbol: None,
+            no_repeat: false,
+        }
+    }
+}
+
+impl CharClass {
+    const ALL: [CharClass; 4] = [
+        CharClass::Upper,
+        CharClass::Lower,
+        CharClass::Number,
+        CharClass::Symbol,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            CharClass::Upper => "uppercase",
+            CharClass::Lower => "lowercase",
+            CharClass::Number => "number",
+            CharClass::Symbol => "symbol",
+        }
+    }
+
+    fn flag(self) -> &'static str {
+        match self {
+            CharClass::Upper => "upper",
+            CharClass::Lower => "lower",
+            CharClass::Number => "number",
+            CharClass::Symbol => "symbol",
+        }
+    }
+
+    fn of(c: char) -> Self {
+        if c.is_uppercase() {
+            CharClass::Upper
+        } else if c.is_lowercase() {
+            CharClass::Lower
+        } else if c.is_numeric() {
+            CharClass::Number
+        } else {
+            CharClass::Symbol
+        }
+    }
+}
+
+// The characters of one class with how many of them a password may hold
+#[derive(Debug)]
+struct ClassPool {
+    chars: Vec<char>,
+    min: usize,
+    max: usize,
+}
+
+impl PasswordPolicy {
+    // The characters a class draws from once the symbol override, ambiguity and exclusions apply
+    pub fn class_chars(&self, class: CharClass) -> Vec<char> {
+        let chars = match class {
+            CharClass::Upper => UPPER,
+            CharClass::Lower => LOWER,
+            CharClass::Number => NUMBER,
+            CharClass::Symbol => self.symbols.as_deref().unwrap_or(SYMBOL),
+        };
+        let mut ret: Vec<char> = Vec::new();
+        for c in chars.chars() {
+            let ambiguous = !self.allow_ambiguous && AMBIGUOUS.contains(c);
+            // classes never overlap, so letters and digits in --symbols are ignored
+            if !ambiguous
+                && !self.exclude.contains(c)
+                && !ret.contains(&c)
+                && CharClass::of(c) == class
+            {
+                ret.push(c);
+            }
+        }
+        ret
+    }
+
+    // Every character the policy draws from, regardless of the class limits
+    pub fn alphabet(&self) -> Vec<char> {
+        match &self.charset {
+            Some(charset) => {
+                let mut chars: Vec<char> = Vec::new();
+                for c in charset.chars() {
+                    if !self.exclude.contains(c) && !chars.contains(&c) {
+                        chars.push(c);
+                    }
+                }
+                chars
+            }
+            None => CharClass::ALL
+                .into_iter()
+                .filter(|&class| self.enabled(class))
+                .flat_map(|class| self.class_chars(class))
+                .collect(),
+        }
+    }
+
+    fn enabled(&self, class: CharClass) -> bool {
+        match class {
+            CharClass::Upper => self.upper,
+            CharClass::Lower => self.lower,
+            CharClass::Number => self.number,
+            CharClass::Symbol => self.symbol,
+        }
+    }
+
+    fn limits(&self, class: CharClass) -> (Option<usize>, Option<usize>) {
+        match class {
+            CharClass::Upper => (self.min_upper, self.max_upper),
+            CharClass::Lower => (self.min_lower, self.max_lower),
+            CharClass::Number => (self.min_number, self.max_number),
+            CharClass::Symbol => (self.min_symbol, self.max_symbol),
+        }
+    }
+
+    // Splits the alphabet into classes and checks their limits can be met within the length
+    fn pools(&self) -> Result<Vec<ClassPool>> {
+        let length = self.length;
+        if length > MAX_PASSWORD_LENGTH {
+            anyhow::bail!(
+                "password length {} is above the {} character limit",
+                length,
+                MAX_PASSWORD_LENGTH
+            )
+        }
+        let mut pools = Vec::new();
+        for class in CharClass::ALL {
+            let (min, max) = self.limits(class);
+            let chars = match &self.charset {
+                Some(charset) => {
+                    let mut chars: Vec<char> = Vec::new();
+                    for c in charset.chars() {
+                        if CharClass::of(c) == class
+                            && !self.exclude.contains(c)
+                            && !chars.contains(&c)
+                        {
+                            chars.push(c);
+                        }
+                    }
+                    chars
+                }
+                None if self.enabled(class) => {
+                    let chars = self.class_chars(class);
+                    if chars.is_empty() {
+                        anyhow::bail!("no {} characters left after --exclude", class.name())
+                    }
+                    chars
+                }
+                None => Vec::new(),
+            };
+            if chars.is_empty() {
+                if min.is_some_and(|min| min > 0) {
+                    anyhow::bail!(
+                        "--min-{} needs {} characters, but there are none to draw from",
+                        class.flag(),
+                        class.name()
+                    )
+                }
+                continue;
+            }
+            if let (Some(min), Some(max)) = (min, max) {
+                if min > max {
+                    anyhow::bail!(
+                        "--min-{} {} is greater than --max-{} {}",
+                        class.flag(),
+                        min,
+                        class.flag(),
+                        max
+                    )
+                }
+            }
+            // a minimum beyond the length is reported below, along with the other minimums
+            let max = max.unwrap_or(length).max(min.unwrap_or(0));
+            let default_min = if self.charset.is_some() { 0 } else { 1 };
+            let min = min.unwrap_or(default_min.min(max));
+            pools.push(ClassPool { chars, min, max });
+        }
+        if pools.is_empty() {
+            match self.charset {
+                Some(_) => anyhow::bail!("no characters left in --charset after --exclude"),
+                None => anyhow::bail!(
+                    "must specify at least one type of [uppercase lowercase number symbol]"
+                ),
+            }
+        }
+        let min = pools.iter().map(|p| p.min).sum::<usize>();
+        let max = pools.iter().map(|p| p.max).sum::<usize>();
+        if min > length {
+            anyhow::bail!(
+                "password length {} is too short for the {} characters the class minimums require",
+                length,
+                min
+            )
+        }
+        if max < length {
+            anyhow::bail!(
+                "the class maximums allow only {} characters, fewer than the password length {}",
+                max,
+                length
+            )
+        }
+        Ok(pools)
+    }
+}
+
+// ln(n!) for n up to the password length
+fn ln_factorials(n: usize) -> Vec<f64> {
+    let mut ret = vec![0.0; n + 1];
+    for i in 1..=n {
+        ret[i] = ret[i - 1] + (i as f64).ln();
+    }
+    ret
+}
+
+// The counts of pool i that leave a length the later pools can fill, out of m characters
+fn take_range(pools: &[ClassPool], i: usize, m: usize) -> Option<(usize, usize)> {
+    let rest = &pools[i + 1..];
+    let rest_min = rest.iter().map(|p| p.min).sum::<usize>();
+    let rest_max = rest.iter().map(|p| p.max).sum::<usize>();
+    let lo = pools[i].min.max(m.saturating_sub(rest_max));
+    let hi = pools[i].max.min(m.checked_sub(rest_min)?);
+    (lo <= hi).then_some((lo, hi))
+}
+
+// counts[i][m]: ln of how many strings of length m the pools from i on can form within their limits
+fn ln_counts(pools: &[ClassPool], length: usize, ln_fact: &[f64]) -> Vec<Vec<f64>> {
+    // terms below this share of the largest one vanish in an f64 sum
+    const NEGLIGIBLE: f64 = 1e-18;
+    let mut counts = vec![vec![f64::NEG_INFINITY; length + 1]; pools.len() + 1];
+    counts[pools.len()][0] = 0.0;
+    for i in (0..pools.len()).rev() {
+        let ln_size = (pools[i].chars.len() as f64).ln();
+        let (head, tail) = counts.split_at_mut(i + 1);
+        let (here, rest) = (&mut head[i], &tail[0]);
+        let mut peak = 0;
+        for m in 0..=length {
+            let Some((lo, hi)) = take_range(pools, i, m) else {
+                continue;
+            };
+            let term = |c: usize| {
+                ln_fact[m] - ln_fact[c] - ln_fact[m - c] + c as f64 * ln_size + rest[m - c]
+            };
+            // the terms are log-concave in c, so they rise to a single peak and fall away;
+            // summing outwards from it until they vanish keeps long passwords fast
+            let mut p = peak.clamp(lo, hi);
+            while p < hi && term(p + 1) > term(p) {
+                p += 1;
+            }
+            while p > lo && term(p - 1) > term(p) {
+                p -= 1;
+            }
+            peak = p;
+            let top = term(p);
+            let share = |c: usize| (term(c) - top).exp();
+            let above = (p + 1..=hi).map(share).take_while(|&s| s >= NEGLIGIBLE);
+            let below = (lo..p).rev().map(share).take_while(|&s| s >= NEGLIGIBLE);
+            let sum = 1.0 + above.sum::<f64>() + below.sum::<f64>();
+            here[m] = top + sum.ln();
+        }
+    }
+    counts
+}
+
+pub fn process_genpass(policy: &PasswordPolicy) -> Result<String> {
+    generate_password(&mut rand::thread_rng(), policy)
+}
+
+// Same as process_genpass, drawing from the given rng so callers can seed it.
+// Every password that meets the policy is equally likely: the number of characters
+// from each class is drawn in proportion to how many passwords have that split,
+// then the characters are drawn and shuffled.
+pub fn generate_password(mut rng: &mut impl Rng, policy: &PasswordPolicy) -> Result<String> {
+    const ATTEMPTS: usize = 10_000;
+    let length = policy.length;
+    let pools = policy.pools()?;
+    let ln_fact = ln_factorials(length);
+    let counts = ln_counts(&pools, length, &ln_fact);
+
+    for _ in 0..ATTEMPTS {
+        let mut password = Vec::with_capacity(length);
+        let mut left = length;
+        for (i, pool) in pools.iter().enumerate() {
+            let ln_size = (pool.chars.len() as f64).ln();
+            let mut target = rng.gen::<f64>();
+            let (lo, hi) = take_range(&pools, i, left).expect("pools fit the length");
+            let mut take = lo;
+            for c in lo..=hi {
+                let ln_choose = ln_fact[left] - ln_fact[c] - ln_fact[left - c];
+                let rest = counts[i + 1][left - c];
+                target -= (ln_choose + c as f64 * ln_size + rest - counts[i][left]).exp();
+                take = c;
+                if target <= 0.0 {
+                    break;
+                }
+            }
+            for _ in 0..take {
+                password.push(*pool.chars.choose(&mut rng).expect("pool won't be empty"));
+            }
+            left -= take;
+        }
+
+        password.shuffle(&mut rng);
+
+        // rejecting keeps the remaining passwords equally likely
+        if !policy.no_repeat || !has_repeat_or_sequence(&password) {
+            return Ok(password.into_iter().collect());
+        }
+    }
+    anyhow::bail!(
+        "could not avoid repeated or sequential characters after {} attempts, allow more characters",
+        ATTEMPTS
+    )
+}
+
+// blake3 derive_key context; changing it would change every derived password
+const DERIVE_CONTEXT: &str = "rcli 2024-06-01 genpass derive v1";
+// Long enough for any site, short enough for the exact counts below to stay quick
+const MAX_DERIVED_LENGTH: usize = 256;
+
+// A reproducible password for a site and login, so nothing needs storing. As in
+// generate_password, the class split is drawn in proportion to how many passwords have
+// it, then the characters are drawn and shuffled; here the counts are exact integers
+// and every draw is integer rejection sampling from a blake3 output stream. Nothing
+// depends on rand or floating point, so the output stays the same across versions and
+// platforms; the test vectors below pin it.
+pub fn derive_password(
+    master_key: &[u8],
+    site: &str,
+    login: &str,
+    counter: u64,
+    policy: &PasswordPolicy,
+) -> Result<String> {
+    const ATTEMPTS: usize = 10_000;
+    if master_key.len() < 16 {
+        anyhow::bail!("master key is too short, use at least 16 bytes")
+    }
+    let length = policy.length;
+    if length > MAX_DERIVED_LENGTH {
+        anyhow::bail!(
+            "derived passwords are at most {} characters",
+            MAX_DERIVED_LENGTH
+        )
+    }
+    let pools = policy.pools()?;
+    let binomials = binomials(length);
+    let powers = pools
+        .iter()
+        .map(|p| {
+            let size = BigUint::from(p.chars.len());
+            (0..=p.max.min(length))
+                .scan(BigUint::from(1u8), |power, _| {
+                    let ret = power.clone();
+                    *power *= &size;
+                    Some(ret)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let counts = exact_counts(&pools, length, &binomials, &powers);
+
+    // length-prefixed, so ("ab", "c") and ("a", "bc") can't collide; domains ignore case
+    let mut material = master_key.to_vec();
+    for field in [site.trim().to_lowercase().as_bytes(), login.as_bytes()] {
+        material.extend((field.len() as u64).to_le_bytes());
+        material.extend(field);
+    }
+    material.extend(counter.to_le_bytes());
+    let seed = blake3::derive_key(DERIVE_CONTEXT, &material);
+    let mut stream = DeriveStream(blake3::Hasher::new_keyed(&seed).finalize_xof());
+
+    for _ in 0..ATTEMPTS {
+        let mut password = Vec::with_capacity(length);
+        let mut pick = stream.below_big(&counts[0][length]);
+        let mut left = length;
+        for (i, pool) in pools.iter().enumerate() {
+            let (lo, hi) = take_range(&pools, i, left).expect("the policy has passwords");
+            let mut take = hi;
+            for c in lo..=hi {
+                let weight = &binomials[left][c] * &powers[i][c] * &counts[i + 1][left - c];
+                if pick < weight {
+                    // what is left of the pick is uniform over the later pools' strings
+                    pick %= &counts[i + 1][left - c];
+                    take = c;
+                    break;
+                }
+                pick -= weight;
+            }
+            for _ in 0..take {
+                password.push(pool.chars[stream.below(pool.chars.len())]);
+            }
+            left -= take;
+        }
+        for i in (1..password.len()).rev() {
+            password.swap(i, stream.below(i + 1));
+        }
+        if !(policy.no_repeat && has_repeat_or_sequence(&password)) {
+            return Ok(password.into_iter().collect());
+        }
+    }
+    anyhow::bail!(
+        "no derived password avoided repeats and sequences in {} tries, allow more characters",
+        ATTEMPTS
+    )
+}
+
+// A key file that is text loses the trailing newline and spaces an editor may leave;
+// any other key is used byte for byte, as a binary key can end in whitespace bytes
+pub fn master_key(content: &[u8]) -> &[u8] {
+    match std::str::from_utf8(content) {
+        Ok(text) => text.trim_end().as_bytes(),
+        Err(_) => content,
+    }
+}
+
+// The blake3 output stream a derived password is drawn from
+struct DeriveStream(blake3::OutputReader);
+
+impl DeriveStream {
+    fn below(&mut self, n: usize) -> usize {
+        let n = n as u64;
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let mut buf = [0; 8];
+            self.0.fill(&mut buf);
+            let value = u64::from_le_bytes(buf);
+            if value < zone {
+                return (value % n) as usize;
+            }
+        }
+    }
+
+    // Draws as many bits as n has until the value falls below it, which takes two tries at most on average
+    fn below_big(&mut self, n: &BigUint) -> BigUint {
+        let bits = n.bits();
+        let mut buf = vec![0; bits.div_ceil(8) as usize];
+        loop {
+            self.0.fill(&mut buf);
+            if bits % 8 != 0 {
+                let last = buf.len() - 1;
+                buf[last] &= (1u8 << (bits % 8)) - 1;
+            }
+            let value = BigUint::from_bytes_le(&buf);
+            if &value < n {
+                return value;
+            }
+        }
+    }
+}
+
+// binomials[m][c] = m choose c, for m up to length
+fn binomials(length: usize) -> Vec<Vec<BigUint>> {
+    let mut rows: Vec<Vec<BigUint>> = vec![vec![BigUint::from(1u8)]];
+    for m in 1..=length {
+        let prev = &rows[m - 1];
+        let row = (0..=m)
+            .map(|c| match c {
+                c if c == 0 || c == m => BigUint::from(1u8),
+                c => &prev[c - 1] + &prev[c],
+            })
+            .collect();
+        rows.push(row);
+    }
+    rows
+}
+
+// As ln_counts, but exact: counts[i][m] is how many strings of length m the pools from i on can form
+fn exact_counts(
+    pools: &[ClassPool],
+    length: usize,
+    binomials: &[Vec<BigUint>],
+    powers: &[Vec<BigUint>],
+) -> Vec<Vec<BigUint>> {
+    let mut counts = vec![vec![BigUint::ZERO; length + 1]; pools.len() + 1];
+    counts[pools.len()][0] = BigUint::from(1u8);
+    for i in (0..pools.len()).rev() {
+        for m in 0..=length {
+            let Some((lo, hi)) = take_range(pools, i, m) else {
+                continue;
+            };
+            let count = (lo..=hi)
+                .map(|c| &binomials[m][c] * &powers[i][c] * &counts[i + 1][m - c])
+                .sum();
+            counts[i][m] = count;
+        }
+    }
+    counts
+}
+
+// log2 of how many passwords meet the policy, an upper bound when no_repeat rejects some of them
+pub fn password_entropy(policy: &PasswordPolicy) -> Result<f64> {
+    let length = policy.length;
+    let pools = policy.pools()?;
+    let counts = ln_counts(&pools, length, &ln_factorials(length));
+    Ok(counts[0][length] / std::f64::consts::LN_2)
+}
+
+fn has_repeat_or_sequence(password: &[char]) -> bool {
+    let step = |a: char, b: char| b as i64 - a as i64;
+    password.windows(2).any(|w| w[0] == w[1])
+        || password.windows(3).any(|w| {
+            let (d1, d2) = (step(w[0], w[1]), step(w[1], w[2]));
+            d1 == d2 && d1.abs() == 1 && w.iter().all(|c| c.is_alphanumeric())
+        })
+}
+
+// What genpass draws from; a strength target may grow it by a character or a word at a time
+#[derive(Debug, Clone)]
+pub enum PasswordSource {
+    Chars(PasswordPolicy),
+    Passphrase(Wordlist, PassphraseSpec),
+    Pattern(PasswordPattern, PasswordPolicy),
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StrengthTarget {
+    pub min_score: Option<u8>,
+    pub min_entropy_bits: Option<f64>,
+}
+
+impl PasswordSource {
+    pub fn generate(&self, rng: &mut impl Rng) -> Result<String> {
+        match self {
+            PasswordSource::Chars(policy) => generate_password(rng, policy),
+            PasswordSource::Passphrase(wordlist, spec) => generate_passphrase(rng, wordlist, spec),
+            PasswordSource::Pattern(pattern, policy) => pattern.generate(rng, policy),
+        }
+    }
+
+    pub fn entropy(&self) -> Result<f64> {
+        match self {
+            PasswordSource::Chars(policy) => password_entropy(policy),
+            PasswordSource::Passphrase(wordlist, spec) => Ok(passphrase_entropy(wordlist, spec)),
+            PasswordSource::Pattern(pattern, policy) => pattern.entropy(policy),
+        }
+    }
+
+    // Characters or words, None for a pattern, which fixes its own shape
+    fn length(&self) -> Option<usize> {
+        match self {
+            PasswordSource::Chars(policy) => Some(policy.length),
+            PasswordSource::Passphrase(_, spec) => Some(spec.words),
+            PasswordSource::Pattern(..) => None,
+        }
+    }
+
+    // None past the class maximums or MAX_PASSWORD_LENGTH, and for a pattern
+    fn with_length(&self, length: usize) -> Option<Self> {
+        match self {
+            PasswordSource::Chars(policy) => {
+                let policy = PasswordPolicy {
+                    length,
+                    ..policy.clone()
+                };
+                policy.pools().ok()?;
+                Some(PasswordSource::Chars(policy))
+            }
+            PasswordSource::Passphrase(wordlist, spec) if length <= MAX_PASSWORD_LENGTH => {
+                let spec = PassphraseSpec {
+                    words: length,
+                    ..spec.clone()
+                };
+                Some(PasswordSource::Passphrase(wordlist.clone(), spec))
+            }
+            PasswordSource::Passphrase(..) | PasswordSource::Pattern(..) => None,
+        }
+    }
+
+    fn lengthen(&self) -> Option<Self> {
+        self.with_length(self.length()? + 1)
+    }
+
+    // The shortest grown source with at least min_bits of entropy. Entropy never falls as
+    // the source grows, so both the longest allowed length and the shortest one that is
+    // enough are found by bisection.
+    fn grow_to_entropy(&self, min_bits: f64) -> Result<Self> {
+        let start = self.length().unwrap_or_default();
+        let (mut lo, mut hi) = (start, start.max(MAX_PASSWORD_LENGTH));
+        while lo < hi {
+            let mid = lo + (hi - lo).div_ceil(2);
+            match self.with_length(mid) {
+                Some(_) => lo = mid,
+                None => hi = mid - 1,
+            }
+        }
+        let longest = self.with_length(lo).unwrap_or_else(|| self.clone());
+        let bits = longest.entropy()?;
+        if bits < min_bits {
+            anyhow::bail!(
+                "at most {:.2} bits of entropy fit in {}, short of --min-entropy-bits {}; {}",
+                bits,
+                longest.size(),
+                min_bits,
+                longest.hint()
+            )
+        }
+        let (mut lo, mut hi) = (start, lo);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let grown = self.with_length(mid).expect("shorter than the longest");
+            if grown.entropy()? >= min_bits {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        Ok(self.with_length(lo).unwrap_or_else(|| self.clone()))
+    }
+
+    fn size(&self) -> String {
+        match self {
+            PasswordSource::Chars(policy) => format!("{} characters", policy.length),
+            PasswordSource::Passphrase(_, spec) => format!("{} words", spec.words),
+            PasswordSource::Pattern(..) => "the pattern".into(),
+        }
+    }
+
+    fn hint(&self) -> &'static str {
+        match self {
+            PasswordSource::Chars(_) => "raise the class maximums or allow more characters",
+            PasswordSource::Passphrase(..) => "use a larger wordlist",
+            PasswordSource::Pattern(..) => "a pattern never grows, so make it longer",
+        }
+    }
+}
+
+// Generates a password that meets the target, growing the source first until its entropy
+// is high enough, then whenever a round of attempts all score too low. The source keeps
+// its new size, so the passwords that follow start from it.
+pub fn generate_strong(
+    rng: &mut impl Rng,
+    source: &mut PasswordSource,
+    target: &StrengthTarget,
+) -> Result<String> {
+    // zxcvbn judges random passwords fairly well, so a handful of tries per size is plenty
+    const ATTEMPTS: usize = 20;
+    // zxcvbn only looks at this many characters, so growing further can't raise the score
+    const ZXCVBN_MAX_LENGTH: usize = 100;
+
+    if let Some(min_bits) = target.min_entropy_bits {
+        if !min_bits.is_finite() {
+            anyhow::bail!("--min-entropy-bits must be a finite number")
+        }
+        if source.entropy()? < min_bits {
+            *source = source.grow_to_entropy(min_bits)?;
+        }
+    }
+
+    let Some(min_score) = target.min_score else {
+        return source.generate(rng);
+    };
+    let mut best = 0;
+    loop {
+        let mut longest = 0;
+        for _ in 0..ATTEMPTS {
+            let password = source.generate(rng)?;
+            let score = zxcvbn::zxcvbn(&password, &[]).map_or(0, |e| e.score());
+            if score >= min_score {
+                return Ok(password);
+            }
+            best = best.max(score);
+            longest = longest.max(password.chars().count());
+        }
+        match source.lengthen() {
+            Some(grown) if longest < ZXCVBN_MAX_LENGTH => *source = grown,
+            _ => anyhow::bail!(
+                "no password from {} reached --min-score {}, the best of {} attempts scored {}; {}",
+                source.size(),
+                min_score,
+                ATTEMPTS,
+                best,
+                source.hint()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GeneratedPassword {
+    pub password: String,
+    #[serde(flatten)]
+    pub strength: Option<PasswordStrength>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PasswordStrength {
+    pub score: u8,
+    pub entropy_bits: f64,
+    // at 10k guesses per second, i.e. a slow password hash
+    pub crack_time: String,
+}
+
+impl PasswordStrength {
+    pub fn estimate(password: &str, entropy_bits: f64) -> Result<Self> {
+        let estimate = zxcvbn::zxcvbn(password, &[])?;
+        Ok(Self {
+            score: estimate.score(),
+            entropy_bits: (entropy_bits * 100.0).round() / 100.0,
+            crack_time: estimate
+                .crack_times()
+                .offline_slow_hashing_1e4_per_second()
+                .to_string(),
+        })
+    }
+}
+
+pub fn format_passwords(passwords: &[GeneratedPassword], format: PasswordFormat) -> Result<String> {
+    let ret = match format {
+        PasswordFormat::Plain => passwords
+            .iter()
+            .map(|p| match &p.strength {
+                Some(s) => format!(
+                    "{}\t{}\t{:.2}\t{}\n",
+                    p.password, s.score, s.entropy_bits, s.crack_time
+                ),
+                None => format!("{}\n", p.password),
+            })
+            .collect(),
+        PasswordFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            let strength = passwords.first().is_some_and(|p| p.strength.is_some());
+            if strength {
+                writer.write_record(["password", "score", "entropy_bits", "crack_time"])?;
+            } else {
+                writer.write_record(["password"])?;
+            }
+            for p in passwords {
+                match &p.strength {
+                    Some(s) => writer.write_record([
+                        p.password.clone(),
+                        s.score.to_string(),
+                        s.entropy_bits.to_string(),
+                        s.crack_time.clone(),
+                    ])?,
+                    None => writer.write_record([&p.password])?,
+                }
+            }
+            String::from_utf8(writer.into_inner()?)?
+        }
+        PasswordFormat::Json => serde_json::to_string_pretty(passwords)? + "\n",
+    };
+    Ok(ret)
+}
+
+#[derive(Debug, Clone)]
+pub struct Wordlist(Vec<String>);
+
+#[derive(Debug, Clone)]
+pub struct PassphraseSpec {
+    pub words: usize,
+    pub separator: String,
+    pub case: WordCase,
+    pub digits: usize,
+    pub symbols: usize,
+    pub digit_chars: Vec<char>,
+    pub symbol_chars: Vec<char>,
+}
+
+impl Wordlist {
+    pub fn load(path: &str, separator: &str) -> Result<Self> {
+        Self::parse(&String::from_utf8(get_content(path)?)?, separator)
+    }
+
+    // One word per line, or diceware's "11111<TAB>word" lines. Duplicates would skew the
+    // entropy, and so would words holding the separator ("t-shirt" against "t" and "shirt"),
+    // so both are dropped.
+    fn parse(content: &str, separator: &str) -> Result<Self> {
+        let mut seen = HashSet::new();
+        let words = content
+            .lines()
+            .filter_map(|line| line.split_whitespace().last())
+            .filter(|word| separator.is_empty() || !word.contains(separator))
+            .filter(|word| seen.insert(*word))
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if words.len() < 2 {
+            anyhow::bail!("wordlist needs at least two distinct words without the separator")
+        }
+        Ok(Self(words))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl WordCase {
+    fn apply(self, word: &str) -> String {
+        match self {
+            WordCase::Lower => word.to_lowercase(),
+            WordCase::Upper => word.to_uppercase(),
+            WordCase::Title => {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            }
+        }
+    }
+}
+
+pub fn generate_passphrase(
+    mut rng: &mut impl Rng,
+    wordlist: &Wordlist,
+    spec: &PassphraseSpec,
 ) -> Result<String> {
-    let mut rng = rand::thread_rng();
-    let mut password = Vec::new();
-    let mut chars = Vec::new();
+    if spec.words == 0 {
+        anyhow::bail!("passphrase needs at least one word")
+    }
+    let mut tokens = (0..spec.words)
+        .map(|_| {
+            let word = wordlist
+                .0
+                .choose(&mut rng)
+                .expect("wordlist won't be empty");
+            spec.case.apply(word)
+        })
+        .collect::<Vec<_>>();
+    // injected characters form their own token, placed between any two words or at either end
+    for (count, chars) in [
+        (spec.digits, &spec.digit_chars),
+        (spec.symbols, &spec.symbol_chars),
+    ] {
+        if count > 0 {
+            if chars.is_empty() {
+                anyhow::bail!("no characters left to insert into the passphrase")
+            }
+            let token = (0..count)
+                .map(|_| *chars.choose(&mut rng).expect("chars won't be empty"))
+                .collect();
+            let at = rng.gen_range(0..=tokens.len());
+            tokens.insert(at, token);
+        }
+    }
+    Ok(tokens.join(&spec.separator))
+}
 
-    if upper {
-        chars.extend_from_slice(UPPER);
-        password.push(*UPPER.choose(&mut rng).expect("UPPER won't be empty"));
+// Exact as long as the words contain no separator, digits or symbols, so that
+// every sequence of choices produces a different passphrase
+pub fn passphrase_entropy(wordlist: &Wordlist, spec: &PassphraseSpec) -> f64 {
+    let mut bits = spec.words as f64 * (wordlist.len() as f64).log2();
+    let mut tokens = spec.words;
+    for (count, chars) in [
+        (spec.digits, &spec.digit_chars),
+        (spec.symbols, &spec.symbol_chars),
+    ] {
+        if count > 0 {
+            bits += count as f64 * (chars.len() as f64).log2();
+            bits += ((tokens + 1) as f64).log2();
+            tokens += 1;
+        }
     }
-    if lower {
-        chars.extend_from_slice(LOWER);
-        password.push(*LOWER.choose(&mut rng).expect("LOWER won't be empty"));
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn spec(words: usize, digits: usize, symbols: usize) -> PassphraseSpec {
+        PassphraseSpec {
+            words,
+            separator: "-".into(),
+            case: WordCase::Title,
+            digits,
+            symbols,
+            digit_chars: PasswordPolicy::default().class_chars(CharClass::Number),
+            symbol_chars: PasswordPolicy::default().class_chars(CharClass::Symbol),
+        }
+    }
+
+    #[test]
+    fn test_passphrase() -> Result<()> {
+        let wordlist = Wordlist::load("fixtures/wordlist.txt", "-")?;
+        // t-shirt and felt-tip are dropped, as they'd read as two words
+        assert_eq!(wordlist.len(), 64);
+        assert!(!wordlist.0.iter().any(|w| w.contains('-')));
+        assert_eq!(Wordlist::load("fixtures/wordlist.txt", "")?.len(), 66);
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..20 {
+            let passphrase = generate_passphrase(&mut rng, &wordlist, &spec(5, 2, 1))?;
+            let parts = passphrase.split('-').collect::<Vec<_>>();
+            assert_eq!(parts.len(), 7);
+            assert_eq!(
+                parts
+                    .iter()
+                    .filter(|p| p.len() == 2 && p.chars().all(|c| c.is_ascii_digit()))
+                    .count(),
+                1
+            );
+            assert_eq!(zxcvbn::zxcvbn(&passphrase, &[])?.score(), 4);
+        }
+        Ok(())
     }
-    if number {
-        chars.extend_from_slice(NUMBER);
-        password.push(*NUMBER.choose(&mut rng).expect("NUMBER won't be empty"));
+
+    #[test]
+    fn test_passphrase_entropy() -> Result<()> {
+        let wordlist = Wordlist::parse(
+            "11111\tone\n11112\ttwo\n11113\tthree\n11114\tfour\none\n",
+            "-",
+        )?;
+        assert_eq!(wordlist.len(), 4);
+        assert_eq!(passphrase_entropy(&wordlist, &spec(3, 0, 0)), 6.0);
+        // a digit (log2 9) in one of 4 places, then a symbol (log2 9) in one of 5
+        let bits = 6.0 + 2.0 * 9f64.log2() + 4f64.log2() + 5f64.log2();
+        assert!((passphrase_entropy(&wordlist, &spec(3, 1, 1)) - bits).abs() < 1e-9);
+        assert!(Wordlist::parse("same\nsame\n", "-").is_err());
+        assert!(Wordlist::parse("a-b\nc-d\ne\n", "-").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_password_policy() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let policy = PasswordPolicy {
+            length: 64,
+            symbols: Some("-+=".into()),
+            exclude: "aeiou".into(),
+            ..Default::default()
+        };
+        let password = generate_password(&mut rng, &policy)?;
+        assert_eq!(password.chars().count(), 64);
+        assert!(password.chars().any(|c| "-+=".contains(c)));
+        assert!(!password.contains(|c| "aeiou!@#".contains(c) || AMBIGUOUS.contains(c)));
+
+        let policy = PasswordPolicy {
+            charset: Some("ab".into()),
+            exclude: "b".into(),
+            ..Default::default()
+        };
+        assert_eq!(generate_password(&mut rng, &policy)?, "a".repeat(16));
+
+        let policy = PasswordPolicy {
+            allow_ambiguous: true,
+            ..Default::default()
+        };
+        assert_eq!(policy.class_chars(CharClass::Number).len(), 10);
+        let policy = PasswordPolicy {
+            exclude: NUMBER.into(),
+            ..Default::default()
+        };
+        let err = generate_password(&mut rng, &policy).unwrap_err();
+        assert_eq!(err.to_string(), "no number characters left after --exclude");
+        Ok(())
     }
-    if symbol {
-        chars.extend_from_slice(SYMBOL);
-        password.push(*SYMBOL.choose(&mut rng).expect("SYMBOL won't be empty"));
+
+    #[test]
+    fn test_password_limits() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(3);
+        let policy = PasswordPolicy {
+            length: 12,
+            min_number: Some(3),
+            min_symbol: Some(2),
+            max_upper: Some(1),
+            no_repeat: true,
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            let password = generate_password(&mut rng, &policy)?
+                .chars()
+                .collect::<Vec<_>>();
+            let count = |class| {
+                password
+                    .iter()
+                    .filter(|&&c| CharClass::of(c) == class)
+                    .count()
+            };
+            assert!(count(CharClass::Number) >= 3);
+            assert!(count(CharClass::Symbol) >= 2);
+            assert_eq!(count(CharClass::Upper), 1);
+            assert!(!has_repeat_or_sequence(&password));
+        }
+
+        let mut infeasible = |policy: PasswordPolicy| generate_password(&mut rng, &policy).is_err();
+        assert!(infeasible(PasswordPolicy {
+            length: 4,
+            min_number: Some(3),
+            ..Default::default()
+        }));
+        assert!(infeasible(PasswordPolicy {
+            length: 8,
+            upper: false,
+            lower: false,
+            max_number: Some(3),
+            max_symbol: Some(3),
+            ..Default::default()
+        }));
+        assert!(infeasible(PasswordPolicy {
+            symbol: false,
+            min_symbol: Some(1),
+            ..Default::default()
+        }));
+        assert!(infeasible(PasswordPolicy {
+            charset: Some("a".into()),
+            length: 2,
+            no_repeat: true,
+            ..Default::default()
+        }));
+        Ok(())
     }
 
-    if password.len() as u8 > length {
-        anyhow::bail!("password length is too short")
+    #[test]
+    fn test_strength_target() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(13);
+        let mut source = PasswordSource::Chars(PasswordPolicy {
+            length: 4,
+            ..Default::default()
+        });
+        let target = StrengthTarget {
+            min_score: Some(4),
+            min_entropy_bits: Some(60.0),
+        };
+        for _ in 0..10 {
+            let password = generate_strong(&mut rng, &mut source, &target)?;
+            assert_eq!(zxcvbn::zxcvbn(&password, &[])?.score(), 4);
+        }
+        // grown just far enough, one character less falls short
+        let PasswordSource::Chars(policy) = &source else {
+            unreachable!()
+        };
+        assert!(password_entropy(policy)? >= 60.0);
+        let shorter = PasswordPolicy {
+            length: policy.length - 1,
+            ..policy.clone()
+        };
+        assert!(password_entropy(&shorter)? < 60.0);
+
+        let mut capped = PasswordSource::Chars(PasswordPolicy {
+            length: 4,
+            max_upper: Some(1),
+            max_lower: Some(1),
+            max_number: Some(1),
+            max_symbol: Some(1),
+            ..Default::default()
+        });
+        let target = StrengthTarget {
+            min_entropy_bits: Some(30.0),
+            ..Default::default()
+        };
+        let err = generate_strong(&mut rng, &mut capped, &target).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("at most 20.15 bits of entropy fit in 4 characters"));
+
+        // targets past what MAX_PASSWORD_LENGTH characters can hold fail rather than spin
+        for min_bits in [f64::INFINITY, f64::NAN, 1e300] {
+            let target = StrengthTarget {
+                min_entropy_bits: Some(min_bits),
+                ..Default::default()
+            };
+            let mut source = PasswordSource::Chars(PasswordPolicy::default());
+            assert!(generate_strong(&mut rng, &mut source, &target).is_err());
+        }
+        let too_long = PasswordPolicy {
+            length: MAX_PASSWORD_LENGTH + 1,
+            ..Default::default()
+        };
+        assert!(generate_password(&mut rng, &too_long).is_err());
+
+        let mut pattern = PasswordSource::Pattern("99".parse()?, PasswordPolicy::default());
+        let target = StrengthTarget {
+            min_score: Some(3),
+            ..Default::default()
+        };
+        assert!(generate_strong(&mut rng, &mut pattern, &target).is_err());
+        Ok(())
     }
 
-    if chars.is_empty() {
-        anyhow::bail!("must specify at least one type of [uppercase lowercase number symbol]")
+    #[test]
+    fn test_password_is_uniform() -> Result<()> {
+        // 19 of the 27 strings over "aB1" hold an uppercase letter, each should come up about 1000 times
+        let mut rng = StdRng::seed_from_u64(11);
+        let policy = PasswordPolicy {
+            length: 3,
+            charset: Some("aB1".into()),
+            min_upper: Some(1),
+            ..Default::default()
+        };
+        let mut seen = std::collections::HashMap::new();
+        for _ in 0..19_000 {
+            *seen
+                .entry(generate_password(&mut rng, &policy)?)
+                .or_insert(0) += 1;
+        }
+        assert_eq!(seen.len(), 19);
+        assert!(
+            seen.values().all(|&n| (850..1150).contains(&n)),
+            "{:?}",
+            seen
+        );
+        Ok(())
     }
 
-    for _ in 0..(length - password.len() as u8) {
-        password.push(*chars.choose(&mut rng).expect("chars won't be empty"));
+    #[test]
+    fn test_derive_password() -> Result<()> {
+        // pinned: if these change, every password users derived before changes with them
+        let key = b"0123456789abcdef0123456789abcdef";
+        let default = PasswordPolicy::default();
+        let vectors = [
+            ("example.com", "alice", 1, &default, "^Br74epCgoPcF2SG"),
+            (" Example.COM", "alice", 1, &default, "^Br74epCgoPcF2SG"),
+            ("example.com", "alice", 2, &default, "6xH^_qM%YxEKVJ@k"),
+            ("example.com", "bob", 1, &default, "_qgGvpyPLNoni3Y8"),
+            (
+                "github.com",
+                "alice",
+                1,
+                &PasswordPolicy {
+                    length: 24,
+                    no_repeat: true,
+                    min_number: Some(4),
+                    ..Default::default()
+                },
+                "91pJm8TP2*YX9hyApMPoq^jQ",
+            ),
+            (
+                "shop.example",
+                "alice",
+                1,
+                &PasswordPolicy {
+                    length: 24,
+                    min_number: Some(18),
+                    ..Default::default()
+                },
+                "&764T965562B8R8421m6275p",
+            ),
+            (
+                "bank.example",
+                "alice",
+                1,
+                &PasswordPolicy {
+                    length: 6,
+                    charset: Some("0123456789".into()),
+                    ..Default::default()
+                },
+                "250304",
+            ),
+        ];
+        for (site, login, counter, policy, expected) in vectors {
+            assert_eq!(
+                derive_password(key, site, login, counter, policy)?,
+                expected
+            );
+        }
+        assert!(derive_password(b"short", "example.com", "alice", 1, &default).is_err());
+        let long = PasswordPolicy {
+            length: 257,
+            ..Default::default()
+        };
+        assert!(derive_password(key, "example.com", "alice", 1, &long).is_err());
+
+        // text key files lose trailing whitespace, binary ones are kept whole
+        let mut text = key.to_vec();
+        text.extend(b" \n");
+        assert_eq!(master_key(&text), key);
+        let binary = b"\xff\xfe\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b\x0c\x0d\x0a";
+        assert_eq!(master_key(binary), binary);
+        assert_eq!(
+            derive_password(master_key(binary), "example.com", "alice", 1, &default)?,
+            "6@x2siJkVSbi1Au3"
+        );
+        Ok(())
     }
 
-    password.shuffle(&mut rng);
+    #[test]
+    fn test_derived_password_is_uniform() -> Result<()> {
+        // as for generate_password, the 19 strings over "aB1" with an uppercase letter
+        // should each come up about 200 times across counters
+        let key = b"0123456789abcdef0123456789abcdef";
+        let policy = PasswordPolicy {
+            length: 3,
+            charset: Some("aB1".into()),
+            min_upper: Some(1),
+            ..Default::default()
+        };
+        let mut seen = std::collections::HashMap::new();
+        for counter in 0..19 * 200 {
+            let password = derive_password(key, "example.com", "alice", counter, &policy)?;
+            *seen.entry(password).or_insert(0) += 1;
+        }
+        assert_eq!(seen.len(), 19);
+        assert!(
+            seen.values().all(|&n| (140..=260).contains(&n)),
+            "{:?}",
+            seen
+        );
+        Ok(())
+    }
 
-    Ok(String::from_utf8(password)?)
+    #[test]
+    fn test_format_passwords() -> Result<()> {
+        let policy = PasswordPolicy {
+            length: 8,
+            charset: Some("ab".into()),
+            ..Default::default()
+        };
+        assert_eq!(password_entropy(&policy)?, 8.0);
+        let passwords = ["abbabaab", "bbbbaaaa"]
+            .into_iter()
+            .map(|p| {
+                Ok(GeneratedPassword {
+                    password: p.into(),
+                    strength: Some(PasswordStrength::estimate(p, 8.0)?),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let csv = format_passwords(&passwords, PasswordFormat::Csv)?;
+        assert!(csv.starts_with("password,score,entropy_bits,crack_time\nabbabaab,"));
+        let json: serde_json::Value =
+            serde_json::from_str(&format_passwords(&passwords, PasswordFormat::Json)?)?;
+        assert_eq!(json[1]["password"], "bbbbaaaa");
+        assert_eq!(json[1]["entropy_bits"], 8.0);
+        assert!(json[1]["score"].as_u64().is_some_and(|s| s <= 4));
+        let plain = format_passwords(&passwords[..1], PasswordFormat::Plain)?;
+        assert!(plain.starts_with("abbabaab\t"));
+        Ok(())
+    }
 }
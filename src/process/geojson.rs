@@ -0,0 +1,287 @@
+use crate::{column_indices, Records, TableWriter};
+use anyhow::Result;
+use csv::StringRecord;
+use serde_json::{json, Map, Value};
+use tracing::warn;
+
+pub enum GeometrySource {
+    LatLon { lat: String, lon: String },
+    Wkt(String),
+}
+
+pub struct GeoJsonWriter {
+    source: GeometrySource,
+}
+
+impl GeoJsonWriter {
+    pub fn new(source: GeometrySource) -> Self {
+        Self { source }
+    }
+}
+
+impl TableWriter for GeoJsonWriter {
+    fn write(&self, headers: &StringRecord, records: Records) -> Result<String> {
+        let geometry_columns = match &self.source {
+            GeometrySource::LatLon { lat, lon } => {
+                column_indices(headers, &[lat.clone(), lon.clone()])?
+            }
+            GeometrySource::Wkt(wkt) => column_indices(headers, std::slice::from_ref(wkt))?,
+        };
+        let mut features = Vec::new();
+        let mut skipped = 0;
+        for (row, record) in records.enumerate() {
+            let record = record?;
+            let geometry = match geometry_columns.as_slice() {
+                [lat, lon] => point_from_lat_lon(
+                    record.get(*lat).unwrap_or_default(),
+                    record.get(*lon).unwrap_or_default(),
+                ),
+                [wkt] => parse_wkt(record.get(*wkt).unwrap_or_default()),
+                _ => unreachable!("geometry is either lat/lon or wkt"),
+            };
+            let geometry = match geometry {
+                Ok(geometry) => geometry,
+                Err(e) => {
+                    warn!("Skipping row {}: {}", row + 1, e);
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let properties = headers
+                .iter()
+                .zip(record.iter())
+                .enumerate()
+                .filter(|(i, _)| !geometry_columns.contains(i))
+                .map(|(_, (k, v))| (k.to_string(), Value::from(v)))
+                .collect::<Map<_, _>>();
+            features.push(json!({
+                "type": "Feature",
+                "geometry": geometry,
+                "properties": properties,
+            }));
+        }
+        if skipped > 0 {
+            warn!("Skipped {} rows with invalid coordinates", skipped);
+        }
+        let collection = json!({ "type": "FeatureCollection", "features": features });
+        Ok(serde_json::to_string(&collection)?)
+    }
+}
+
+fn point_from_lat_lon(lat: &str, lon: &str) -> Result<Value> {
+    let parse = |name: &str, value: &str| -> Result<f64> {
+        value
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| anyhow::anyhow!("{} {:?} is not a number", name, value))
+    };
+    let position = vec![parse("longitude", lon)?, parse("latitude", lat)?];
+    check_position(&position)?;
+    Ok(json!({ "type": "Point", "coordinates": position }))
+}
+
+fn check_position(position: &[f64]) -> Result<()> {
+    match position {
+        [lon, ..] if !(-180.0..=180.0).contains(lon) => {
+            anyhow::bail!("longitude {} is out of range", lon)
+        }
+        [_, lat, ..] if !(-90.0..=90.0).contains(lat) => {
+            anyhow::bail!("latitude {} is out of range", lat)
+        }
+        [_, _] | [_, _, _] => Ok(()),
+        _ => anyhow::bail!("a position needs 2 or 3 numbers, got {}", position.len()),
+    }
+}
+
+#[derive(Debug)]
+enum Coordinates {
+    Position(Vec<f64>),
+    List(Vec<Coordinates>),
+}
+
+impl Coordinates {
+    fn depth(&self) -> usize {
+        match self {
+            Coordinates::Position(_) => 0,
+            Coordinates::List(items) => 1 + items.first().map_or(0, |c| c.depth()),
+        }
+    }
+
+    fn to_value(&self) -> Result<Value> {
+        match self {
+            Coordinates::Position(position) => {
+                check_position(position)?;
+                Ok(json!(position))
+            }
+            Coordinates::List(items) => Ok(Value::Array(
+                items.iter().map(|c| c.to_value()).collect::<Result<_>>()?,
+            )),
+        }
+    }
+}
+
+// Parse a WKT geometry such as "POINT (30 10)" or "POLYGON ((30 10, 40 40, 20 40, 30 10))"
+fn parse_wkt(wkt: &str) -> Result<Value> {
+    let wkt = wkt.trim();
+    let split = wkt
+        .find('(')
+        .ok_or_else(|| anyhow::anyhow!("invalid WKT {:?}", wkt))?;
+    let kind = wkt[..split].trim().to_uppercase();
+    let mut parser = WktParser {
+        chars: wkt[split..].chars().collect(),
+        pos: 0,
+    };
+    let mut coordinates = parser.parse_list()?;
+    parser.skip_whitespace();
+    if parser.pos != parser.chars.len() {
+        anyhow::bail!("unexpected text after WKT geometry {:?}", wkt);
+    }
+    let (kind, depth) = match kind.as_str() {
+        "POINT" => ("Point", 0),
+        "LINESTRING" => ("LineString", 1),
+        "MULTIPOINT" => ("MultiPoint", 1),
+        "POLYGON" => ("Polygon", 2),
+        "MULTILINESTRING" => ("MultiLineString", 2),
+        "MULTIPOLYGON" => ("MultiPolygon", 3),
+        v => anyhow::bail!("unsupported WKT geometry type {:?}", v),
+    };
+    coordinates = match (depth, coordinates) {
+        // "POINT (30 10)" parses as a list holding one position
+        (0, Coordinates::List(mut items)) if items.len() == 1 => items.remove(0),
+        // MULTIPOINT may wrap every point in its own parentheses
+        (1, Coordinates::List(items)) if kind == "MultiPoint" => Coordinates::List(
+            items
+                .into_iter()
+                .map(|c| match c {
+                    Coordinates::List(mut inner) if inner.len() == 1 => inner.remove(0),
+                    c => c,
+                })
+                .collect(),
+        ),
+        (_, coordinates) => coordinates,
+    };
+    if coordinates.depth() != depth {
+        anyhow::bail!("malformed {} coordinates in {:?}", kind, wkt);
+    }
+    Ok(json!({ "type": kind, "coordinates": coordinates.to_value()? }))
+}
+
+struct WktParser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl WktParser {
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        self.skip_whitespace();
+        if self.chars.get(self.pos) != Some(&c) {
+            anyhow::bail!("expected {:?} at position {} of WKT", c, self.pos);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_list(&mut self) -> Result<Coordinates> {
+        self.expect('(')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let item = if self.chars.get(self.pos) == Some(&'(') {
+                self.parse_list()?
+            } else {
+                self.parse_position()?
+            };
+            items.push(item);
+            self.skip_whitespace();
+            match self.chars.get(self.pos) {
+                Some(',') => self.pos += 1,
+                Some(')') => {
+                    self.pos += 1;
+                    return Ok(Coordinates::List(items));
+                }
+                _ => anyhow::bail!("expected ',' or ')' at position {} of WKT", self.pos),
+            }
+        }
+    }
+
+    fn parse_position(&mut self) -> Result<Coordinates> {
+        let start = self.pos;
+        while self
+            .chars
+            .get(self.pos)
+            .is_some_and(|c| !matches!(c, ',' | ')' | '('))
+        {
+            self.pos += 1;
+        }
+        let text = self.chars[start..self.pos].iter().collect::<String>();
+        let position = text
+            .split_whitespace()
+            .map(|n| {
+                n.parse::<f64>()
+                    .map_err(|_| anyhow::anyhow!("{:?} is not a number", n))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Coordinates::Position(position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wkt() -> Result<()> {
+        assert_eq!(
+            parse_wkt("POINT (30 10)")?,
+            json!({ "type": "Point", "coordinates": [30.0, 10.0] })
+        );
+        assert_eq!(
+            parse_wkt("multipoint ((10 40), (40 30))")?["coordinates"],
+            json!([[10.0, 40.0], [40.0, 30.0]])
+        );
+        let polygon = parse_wkt("POLYGON ((30 10, 40 40, 20 40, 30 10))")?;
+        assert_eq!(polygon["type"], "Polygon");
+        assert_eq!(polygon["coordinates"][0][1], json!([40.0, 40.0]));
+        assert!(parse_wkt("POINT (200 10)").is_err());
+        assert!(parse_wkt("POLYGON (30 10, 40 40)").is_err());
+        assert!(parse_wkt("CIRCLE (1 2)").is_err());
+        assert!(parse_wkt("POINT (1 2").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_feature_collection() -> Result<()> {
+        let headers = StringRecord::from(vec!["site", "lat", "lon"]);
+        let records: Records = Box::new(
+            vec![
+                vec!["Allianz Stadium", "45.1096", "7.6413"],
+                vec!["Nowhere", "95", "7"],
+                vec!["Unknown", "", ""],
+            ]
+            .into_iter()
+            .map(|r| Ok(StringRecord::from(r))),
+        );
+        let writer = GeoJsonWriter::new(GeometrySource::LatLon {
+            lat: "lat".into(),
+            lon: "lon".into(),
+        });
+        let ret: Value = serde_json::from_str(&writer.write(&headers, records)?)?;
+        let features = ret["features"].as_array().unwrap();
+        assert_eq!(features.len(), 1);
+        assert_eq!(
+            features[0]["geometry"]["coordinates"],
+            json!([7.6413, 45.1096])
+        );
+        assert_eq!(
+            features[0]["properties"],
+            json!({ "site": "Allianz Stadium" })
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,822 @@
+use anyhow::Result;
+use serde_json::{Map, Number, Value};
+use std::cmp::Ordering;
+use std::str::FromStr;
+
+// A small jq dialect: paths (.a.b, .[0], .[], .[1:3]), pipes, commas, comparisons,
+// and/or, + and -, array and object construction, and a handful of builtins
+#[derive(Debug, Clone)]
+pub enum JsonFilter {
+    Identity,
+    Literal(Value),
+    Field(Box<JsonFilter>, String),
+    Index(Box<JsonFilter>, Box<JsonFilter>),
+    Slice(
+        Box<JsonFilter>,
+        Option<Box<JsonFilter>>,
+        Option<Box<JsonFilter>>,
+    ),
+    Iterate(Box<JsonFilter>),
+    Optional(Box<JsonFilter>),
+    Pipe(Box<JsonFilter>, Box<JsonFilter>),
+    Comma(Box<JsonFilter>, Box<JsonFilter>),
+    Binary(BinaryOp, Box<JsonFilter>, Box<JsonFilter>),
+    Array(Option<Box<JsonFilter>>),
+    Object(Vec<(JsonFilter, JsonFilter)>),
+    Call(String, Vec<JsonFilter>),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum BinaryOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    Add,
+    Sub,
+}
+
+impl JsonFilter {
+    pub fn apply(&self, input: &Value) -> Result<Vec<Value>> {
+        let ret = match self {
+            JsonFilter::Identity => vec![input.clone()],
+            JsonFilter::Literal(value) => vec![value.clone()],
+            JsonFilter::Field(base, name) => base
+                .apply(input)?
+                .iter()
+                .map(|v| match v {
+                    Value::Object(map) => Ok(map.get(name).cloned().unwrap_or(Value::Null)),
+                    Value::Null => Ok(Value::Null),
+                    v => anyhow::bail!("cannot index {} with {:?}", type_name(v), name),
+                })
+                .collect::<Result<_>>()?,
+            JsonFilter::Index(base, index) => {
+                let mut ret = Vec::new();
+                for value in base.apply(input)? {
+                    for index in index.apply(input)? {
+                        ret.push(index_value(&value, &index)?);
+                    }
+                }
+                ret
+            }
+            JsonFilter::Slice(base, from, to) => {
+                let bound = |f: &Option<Box<JsonFilter>>| -> Result<Option<i64>> {
+                    let Some(f) = f else { return Ok(None) };
+                    match f.apply(input)?.as_slice() {
+                        [Value::Number(n)] => Ok(n.as_f64().map(|n| n as i64)),
+                        [Value::Null] => Ok(None),
+                        _ => anyhow::bail!("slice bounds must be numbers"),
+                    }
+                };
+                let (from, to) = (bound(from)?, bound(to)?);
+                base.apply(input)?
+                    .iter()
+                    .map(|v| slice_value(v, from, to))
+                    .collect::<Result<_>>()?
+            }
+            JsonFilter::Iterate(base) => {
+                let mut ret = Vec::new();
+                for value in base.apply(input)? {
+                    match value {
+                        Value::Array(items) => ret.extend(items),
+                        Value::Object(map) => ret.extend(map.into_iter().map(|(_, v)| v)),
+                        v => anyhow::bail!("cannot iterate over {}", type_name(&v)),
+                    }
+                }
+                ret
+            }
+            JsonFilter::Optional(f) => f.apply(input).unwrap_or_default(),
+            JsonFilter::Pipe(left, right) => {
+                let mut ret = Vec::new();
+                for value in left.apply(input)? {
+                    ret.extend(right.apply(&value)?);
+                }
+                ret
+            }
+            JsonFilter::Comma(left, right) => {
+                let mut ret = left.apply(input)?;
+                ret.extend(right.apply(input)?);
+                ret
+            }
+            JsonFilter::Binary(op, left, right) => {
+                let mut ret = Vec::new();
+                for r in right.apply(input)? {
+                    for l in left.apply(input)? {
+                        ret.push(binary(*op, &l, &r)?);
+                    }
+                }
+                ret
+            }
+            JsonFilter::Array(None) => vec![Value::Array(vec![])],
+            JsonFilter::Array(Some(f)) => vec![Value::Array(f.apply(input)?)],
+            JsonFilter::Object(entries) => {
+                // every combination of key and value outputs becomes an object, as in jq
+                let mut ret = vec![Map::new()];
+                for (key, value) in entries {
+                    let keys = key.apply(input)?;
+                    let values = value.apply(input)?;
+                    let mut next = Vec::with_capacity(ret.len() * keys.len() * values.len());
+                    for map in &ret {
+                        for k in &keys {
+                            let Value::String(k) = k else {
+                                anyhow::bail!("object keys must be strings, got {}", type_name(k));
+                            };
+                            for v in &values {
+                                let mut map = map.clone();
+                                map.insert(k.clone(), v.clone());
+                                next.push(map);
+                            }
+                        }
+                    }
+                    ret = next;
+                }
+                ret.into_iter().map(Value::Object).collect()
+            }
+            JsonFilter::Call(name, args) => call(name, args, input)?,
+        };
+        Ok(ret)
+    }
+}
+
+fn call(name: &str, args: &[JsonFilter], input: &Value) -> Result<Vec<Value>> {
+    let ret = match (name, args) {
+        ("empty", []) => vec![],
+        ("select", [cond]) => {
+            if cond.apply(input)?.iter().any(truthy) {
+                vec![input.clone()]
+            } else {
+                vec![]
+            }
+        }
+        ("map", [f]) => {
+            let mut ret = Vec::new();
+            for item in JsonFilter::Iterate(Box::new(JsonFilter::Identity)).apply(input)? {
+                ret.extend(f.apply(&item)?);
+            }
+            vec![Value::Array(ret)]
+        }
+        ("sort_by", [f]) => {
+            let Value::Array(items) = input else {
+                anyhow::bail!("cannot sort {}", type_name(input));
+            };
+            let mut keyed = items
+                .iter()
+                .map(|item| Ok((f.apply(item)?, item.clone())))
+                .collect::<Result<Vec<_>>>()?;
+            keyed.sort_by(|a, b| compare_all(&a.0, &b.0));
+            vec![Value::Array(keyed.into_iter().map(|(_, v)| v).collect())]
+        }
+        ("has", [key]) => key
+            .apply(input)?
+            .iter()
+            .map(|key| match (input, key) {
+                (Value::Object(map), Value::String(k)) => Ok(Value::Bool(map.contains_key(k))),
+                (Value::Array(items), Value::Number(n)) => Ok(Value::Bool(
+                    n.as_f64()
+                        .is_some_and(|n| n >= 0.0 && (n as usize) < items.len()),
+                )),
+                _ => anyhow::bail!("cannot check whether {} has a key", type_name(input)),
+            })
+            .collect::<Result<_>>()?,
+        (name, []) => vec![builtin(name, input)?],
+        (name, args) => anyhow::bail!("{}/{} is not defined", name, args.len()),
+    };
+    Ok(ret)
+}
+
+fn builtin(name: &str, input: &Value) -> Result<Value> {
+    let ret = match (name, input) {
+        ("not", v) => Value::Bool(!truthy(v)),
+        ("length", Value::Null) => Value::from(0),
+        ("length", Value::Bool(_)) => anyhow::bail!("boolean has no length"),
+        ("length", Value::Number(n)) => Value::from(n.as_f64().unwrap_or_default().abs()),
+        ("length", Value::String(s)) => Value::from(s.chars().count()),
+        ("length", Value::Array(items)) => Value::from(items.len()),
+        ("length", Value::Object(map)) => Value::from(map.len()),
+        ("keys", Value::Object(map)) => {
+            let mut keys = map.keys().cloned().collect::<Vec<_>>();
+            keys.sort();
+            Value::from(keys)
+        }
+        ("keys", Value::Array(items)) => Value::from((0..items.len()).collect::<Vec<_>>()),
+        ("values", Value::Object(map)) => Value::Array(map.values().cloned().collect()),
+        ("values", Value::Array(items)) => Value::Array(items.clone()),
+        ("sort", Value::Array(items)) => {
+            let mut items = items.clone();
+            items.sort_by(compare);
+            Value::Array(items)
+        }
+        ("reverse", Value::Array(items)) => Value::Array(items.iter().rev().cloned().collect()),
+        ("first", Value::Array(items)) => items.first().cloned().unwrap_or(Value::Null),
+        ("last", Value::Array(items)) => items.last().cloned().unwrap_or(Value::Null),
+        ("add", Value::Array(items)) => items
+            .iter()
+            .try_fold(Value::Null, |acc, v| binary(BinaryOp::Add, &acc, v))?,
+        ("type", v) => Value::from(type_name(v)),
+        ("tostring", Value::String(s)) => Value::String(s.clone()),
+        ("tostring", v) => Value::String(serde_json::to_string(v)?),
+        ("tonumber", Value::Number(n)) => Value::Number(n.clone()),
+        ("tonumber", Value::String(s)) => number(
+            s.trim()
+                .parse()
+                .map_err(|_| anyhow::anyhow!("cannot parse {:?} as a number", s))?,
+        ),
+        ("keys" | "values" | "sort" | "reverse" | "first" | "last" | "add" | "tonumber", v) => {
+            anyhow::bail!("{} cannot be applied to {}", name, type_name(v))
+        }
+        (name, _) => anyhow::bail!("{}/0 is not defined", name),
+    };
+    Ok(ret)
+}
+
+fn index_value(value: &Value, index: &Value) -> Result<Value> {
+    match (value, index) {
+        (Value::Null, _) => Ok(Value::Null),
+        (Value::Object(map), Value::String(k)) => Ok(map.get(k).cloned().unwrap_or(Value::Null)),
+        (Value::Array(items), Value::Number(n)) => {
+            let i = n.as_f64().unwrap_or_default() as i64;
+            let i = if i < 0 { items.len() as i64 + i } else { i };
+            Ok(usize::try_from(i)
+                .ok()
+                .and_then(|i| items.get(i))
+                .cloned()
+                .unwrap_or(Value::Null))
+        }
+        (v, i) => anyhow::bail!("cannot index {} with {}", type_name(v), type_name(i)),
+    }
+}
+
+fn slice_value(value: &Value, from: Option<i64>, to: Option<i64>) -> Result<Value> {
+    let range = |len: usize| {
+        let clamp = |i: i64| {
+            let i = if i < 0 { len as i64 + i } else { i };
+            i.clamp(0, len as i64) as usize
+        };
+        let from = from.map_or(0, clamp);
+        (from, to.map_or(len, clamp).max(from))
+    };
+    match value {
+        Value::Null => Ok(Value::Null),
+        Value::Array(items) => {
+            let (from, to) = range(items.len());
+            Ok(Value::Array(items[from..to].to_vec()))
+        }
+        Value::String(s) => {
+            let chars = s.chars().collect::<Vec<_>>();
+            let (from, to) = range(chars.len());
+            Ok(Value::String(chars[from..to].iter().collect()))
+        }
+        v => anyhow::bail!("cannot slice {}", type_name(v)),
+    }
+}
+
+fn binary(op: BinaryOp, l: &Value, r: &Value) -> Result<Value> {
+    let ret = match op {
+        BinaryOp::Eq => Value::Bool(compare(l, r) == Ordering::Equal),
+        BinaryOp::Ne => Value::Bool(compare(l, r) != Ordering::Equal),
+        BinaryOp::Lt => Value::Bool(compare(l, r) == Ordering::Less),
+        BinaryOp::Le => Value::Bool(compare(l, r) != Ordering::Greater),
+        BinaryOp::Gt => Value::Bool(compare(l, r) == Ordering::Greater),
+        BinaryOp::Ge => Value::Bool(compare(l, r) != Ordering::Less),
+        BinaryOp::And => Value::Bool(truthy(l) && truthy(r)),
+        BinaryOp::Or => Value::Bool(truthy(l) || truthy(r)),
+        BinaryOp::Add => match (l, r) {
+            (Value::Null, v) | (v, Value::Null) => v.clone(),
+            (Value::Number(a), Value::Number(b)) => number(as_f64(a) + as_f64(b)),
+            (Value::String(a), Value::String(b)) => Value::String(format!("{}{}", a, b)),
+            (Value::Array(a), Value::Array(b)) => Value::Array([a.clone(), b.clone()].concat()),
+            (Value::Object(a), Value::Object(b)) => {
+                let mut map = a.clone();
+                map.extend(b.clone());
+                Value::Object(map)
+            }
+            (a, b) => anyhow::bail!("cannot add {} and {}", type_name(a), type_name(b)),
+        },
+        BinaryOp::Sub => match (l, r) {
+            (Value::Number(a), Value::Number(b)) => number(as_f64(a) - as_f64(b)),
+            (Value::Array(a), Value::Array(b)) => {
+                Value::Array(a.iter().filter(|v| !b.contains(v)).cloned().collect())
+            }
+            (a, b) => anyhow::bail!("cannot subtract {} from {}", type_name(b), type_name(a)),
+        },
+    };
+    Ok(ret)
+}
+
+fn as_f64(n: &Number) -> f64 {
+    n.as_f64().unwrap_or_default()
+}
+
+// Whole numbers are kept as integers so they print without a fraction
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < i64::MAX as f64 {
+        Value::from(n as i64)
+    } else {
+        Number::from_f64(n).map_or(Value::Null, Value::Number)
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+// jq ordering: null < false < true < numbers < strings < arrays < objects
+fn compare(a: &Value, b: &Value) -> Ordering {
+    let rank = |v: &Value| match v {
+        Value::Null => 0,
+        Value::Bool(false) => 1,
+        Value::Bool(true) => 2,
+        Value::Number(_) => 3,
+        Value::String(_) => 4,
+        Value::Array(_) => 5,
+        Value::Object(_) => 6,
+    };
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => as_f64(a).total_cmp(&as_f64(b)),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Array(a), Value::Array(b)) => compare_all(a, b),
+        (Value::Object(a), Value::Object(b)) => {
+            let mut a = a.iter().collect::<Vec<_>>();
+            let mut b = b.iter().collect::<Vec<_>>();
+            a.sort_by(|x, y| x.0.cmp(y.0));
+            b.sort_by(|x, y| x.0.cmp(y.0));
+            let keys = a.iter().map(|e| e.0).cmp(b.iter().map(|e| e.0));
+            keys.then_with(|| {
+                a.iter()
+                    .zip(b.iter())
+                    .map(|(x, y)| compare(x.1, y.1))
+                    .find(|o| o.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        (a, b) => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn compare_all(a: &[Value], b: &[Value]) -> Ordering {
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| compare(x, y))
+        .find(|o| o.is_ne())
+        .unwrap_or_else(|| a.len().cmp(&b.len()))
+}
+
+pub fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(sort_keys),
+        Value::Object(map) => {
+            let mut entries = std::mem::take(map).into_iter().collect::<Vec<_>>();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            for (k, mut v) in entries {
+                sort_keys(&mut v);
+                map.insert(k, v);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Op(&'static str),
+    Punct(char),
+}
+
+fn tokenize(filter: &str) -> Result<Vec<Token>> {
+    let chars = filter.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '.' => {
+                tokens.push(Token::Dot);
+                i += 1;
+            }
+            '"' => {
+                let mut s = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        Some('"') => break,
+                        Some('\\') => {
+                            let escaped = match chars.get(i + 1) {
+                                Some('n') => '\n',
+                                Some('t') => '\t',
+                                Some(&c) => c,
+                                None => anyhow::bail!("unterminated string in filter"),
+                            };
+                            s.push(escaped);
+                            i += 2;
+                        }
+                        Some(&c) => {
+                            s.push(c);
+                            i += 1;
+                        }
+                        None => anyhow::bail!("unterminated string in filter"),
+                    }
+                }
+                i += 1;
+                tokens.push(Token::Str(s));
+            }
+            c if c.is_ascii_digit() => {
+                let start = i;
+                while chars
+                    .get(i)
+                    .is_some_and(|c| c.is_ascii_digit() || *c == '.' || *c == 'e')
+                {
+                    i += 1;
+                }
+                let text = chars[start..i].iter().collect::<String>();
+                tokens.push(Token::Num(text.parse().map_err(|_| {
+                    anyhow::anyhow!("invalid number {} in filter", text)
+                })?));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while chars
+                    .get(i)
+                    .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+                {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            }
+            _ => {
+                let two = chars[i..].iter().take(2).collect::<String>();
+                let op = ["==", "!=", "<=", ">="]
+                    .into_iter()
+                    .find(|op| *op == two)
+                    .or_else(|| {
+                        ["<", ">", "+", "-"]
+                            .into_iter()
+                            .find(|op| op.starts_with(c))
+                    });
+                match (op, c) {
+                    (Some(op), _) => {
+                        tokens.push(Token::Op(op));
+                        i += op.len();
+                    }
+                    (None, '|' | ',' | ':' | ';' | '[' | ']' | '(' | ')' | '{' | '}' | '?') => {
+                        tokens.push(Token::Punct(c));
+                        i += 1;
+                    }
+                    _ => anyhow::bail!("unexpected {:?} at position {} of filter", c, i),
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if !self.eat(&Token::Punct(c)) {
+            anyhow::bail!("expected '{}' in filter, found {:?}", c, self.peek());
+        }
+        Ok(())
+    }
+
+    fn parse_pipe(&mut self) -> Result<JsonFilter> {
+        let mut left = self.parse_comma()?;
+        while self.eat(&Token::Punct('|')) {
+            let right = self.parse_comma()?;
+            left = JsonFilter::Pipe(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_comma(&mut self) -> Result<JsonFilter> {
+        let mut left = self.parse_or()?;
+        while self.eat(&Token::Punct(',')) {
+            let right = self.parse_or()?;
+            left = JsonFilter::Comma(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_or(&mut self) -> Result<JsonFilter> {
+        let mut left = self.parse_and()?;
+        while self.eat(&Token::Ident("or".into())) {
+            let right = self.parse_and()?;
+            left = JsonFilter::Binary(BinaryOp::Or, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<JsonFilter> {
+        let mut left = self.parse_comparison()?;
+        while self.eat(&Token::Ident("and".into())) {
+            let right = self.parse_comparison()?;
+            left = JsonFilter::Binary(BinaryOp::And, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_comparison(&mut self) -> Result<JsonFilter> {
+        let left = self.parse_additive()?;
+        let op = match self.peek() {
+            Some(Token::Op("==")) => BinaryOp::Eq,
+            Some(Token::Op("!=")) => BinaryOp::Ne,
+            Some(Token::Op("<")) => BinaryOp::Lt,
+            Some(Token::Op("<=")) => BinaryOp::Le,
+            Some(Token::Op(">")) => BinaryOp::Gt,
+            Some(Token::Op(">=")) => BinaryOp::Ge,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.parse_additive()?;
+        Ok(JsonFilter::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn parse_additive(&mut self) -> Result<JsonFilter> {
+        let mut left = self.parse_postfix()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Op("+")) => BinaryOp::Add,
+                Some(Token::Op("-")) => BinaryOp::Sub,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.parse_postfix()?;
+            left = JsonFilter::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn parse_postfix(&mut self) -> Result<JsonFilter> {
+        let mut filter = self.parse_primary()?;
+        loop {
+            filter = match self.peek() {
+                Some(Token::Dot) => {
+                    self.pos += 1;
+                    match self.next() {
+                        Some(Token::Ident(name) | Token::Str(name)) => {
+                            JsonFilter::Field(Box::new(filter), name)
+                        }
+                        Some(Token::Punct('[')) => self.parse_brackets(filter)?,
+                        t => anyhow::bail!("expected a field name after '.', found {:?}", t),
+                    }
+                }
+                Some(Token::Punct('[')) => {
+                    self.pos += 1;
+                    self.parse_brackets(filter)?
+                }
+                Some(Token::Punct('?')) => {
+                    self.pos += 1;
+                    JsonFilter::Optional(Box::new(filter))
+                }
+                _ => return Ok(filter),
+            };
+        }
+    }
+
+    // After an opening '[': .[], .[i], .[from:to]
+    fn parse_brackets(&mut self, base: JsonFilter) -> Result<JsonFilter> {
+        let base = Box::new(base);
+        if self.eat(&Token::Punct(']')) {
+            return Ok(JsonFilter::Iterate(base));
+        }
+        let from = match self.peek() {
+            Some(Token::Punct(':')) => None,
+            _ => Some(Box::new(self.parse_pipe()?)),
+        };
+        if self.eat(&Token::Punct(':')) {
+            let to = match self.peek() {
+                Some(Token::Punct(']')) => None,
+                _ => Some(Box::new(self.parse_pipe()?)),
+            };
+            self.expect(']')?;
+            return Ok(JsonFilter::Slice(base, from, to));
+        }
+        self.expect(']')?;
+        let index = from.ok_or_else(|| anyhow::anyhow!("empty index in filter"))?;
+        Ok(JsonFilter::Index(base, index))
+    }
+
+    fn parse_primary(&mut self) -> Result<JsonFilter> {
+        let filter = match self.next() {
+            Some(Token::Dot) => match self.peek() {
+                Some(Token::Ident(_) | Token::Str(_)) => match self.next() {
+                    Some(Token::Ident(name) | Token::Str(name)) => {
+                        JsonFilter::Field(Box::new(JsonFilter::Identity), name)
+                    }
+                    _ => unreachable!("peeked a field name"),
+                },
+                Some(Token::Punct('[')) => {
+                    self.pos += 1;
+                    self.parse_brackets(JsonFilter::Identity)?
+                }
+                _ => JsonFilter::Identity,
+            },
+            Some(Token::Num(n)) => JsonFilter::Literal(number(n)),
+            Some(Token::Op("-")) => match self.next() {
+                Some(Token::Num(n)) => JsonFilter::Literal(number(-n)),
+                t => anyhow::bail!("expected a number after '-', found {:?}", t),
+            },
+            Some(Token::Str(s)) => JsonFilter::Literal(Value::String(s)),
+            Some(Token::Punct('(')) => {
+                let filter = self.parse_pipe()?;
+                self.expect(')')?;
+                filter
+            }
+            Some(Token::Punct('[')) => {
+                if self.eat(&Token::Punct(']')) {
+                    JsonFilter::Array(None)
+                } else {
+                    let filter = self.parse_pipe()?;
+                    self.expect(']')?;
+                    JsonFilter::Array(Some(Box::new(filter)))
+                }
+            }
+            Some(Token::Punct('{')) => self.parse_object()?,
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => JsonFilter::Literal(Value::Bool(true)),
+                "false" => JsonFilter::Literal(Value::Bool(false)),
+                "null" => JsonFilter::Literal(Value::Null),
+                _ => {
+                    let mut args = Vec::new();
+                    // arguments are separated by ';' as in jq
+                    if self.eat(&Token::Punct('(')) {
+                        loop {
+                            args.push(self.parse_pipe()?);
+                            if self.eat(&Token::Punct(')')) {
+                                break;
+                            }
+                            self.expect(';')?;
+                        }
+                    }
+                    JsonFilter::Call(name, args)
+                }
+            },
+            t => anyhow::bail!("unexpected {:?} in filter", t),
+        };
+        Ok(filter)
+    }
+
+    fn parse_object(&mut self) -> Result<JsonFilter> {
+        let mut entries = Vec::new();
+        if self.eat(&Token::Punct('}')) {
+            return Ok(JsonFilter::Object(entries));
+        }
+        loop {
+            let (key, name) = match self.next() {
+                Some(Token::Ident(name) | Token::Str(name)) => {
+                    (JsonFilter::Literal(Value::String(name.clone())), Some(name))
+                }
+                Some(Token::Punct('(')) => {
+                    let key = self.parse_pipe()?;
+                    self.expect(')')?;
+                    (key, None)
+                }
+                t => anyhow::bail!("expected an object key, found {:?}", t),
+            };
+            let value = if self.eat(&Token::Punct(':')) {
+                self.parse_or()?
+            } else {
+                // {name} is shorthand for {name: .name}
+                let name = name.ok_or_else(|| anyhow::anyhow!("computed keys need a value"))?;
+                JsonFilter::Field(Box::new(JsonFilter::Identity), name)
+            };
+            entries.push((key, value));
+            match self.next() {
+                Some(Token::Punct(',')) => {}
+                Some(Token::Punct('}')) => return Ok(JsonFilter::Object(entries)),
+                t => anyhow::bail!("expected ',' or '}}' in object, found {:?}", t),
+            }
+        }
+    }
+}
+
+impl FromStr for JsonFilter {
+    type Err = anyhow::Error;
+    fn from_str(filter: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(filter)?,
+            pos: 0,
+        };
+        if parser.tokens.is_empty() {
+            return Ok(JsonFilter::Identity);
+        }
+        let filter = parser.parse_pipe()?;
+        if let Some(token) = parser.peek() {
+            anyhow::bail!("unexpected {:?} in filter", token);
+        }
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(filter: &str, input: Value) -> Result<Vec<Value>> {
+        filter.parse::<JsonFilter>()?.apply(&input)
+    }
+
+    fn players() -> Value {
+        json!([
+            {"Name": "Buffon", "Position": "Goalkeeper", "Kit Number": 77},
+            {"Name": "Dybala", "Position": "Second Striker", "Kit Number": 10},
+            {"Name": "Ronaldo", "Position": "Centre-Forward", "Kit Number": 7}
+        ])
+    }
+
+    #[test]
+    fn test_paths() -> Result<()> {
+        assert_eq!(run(".", json!(1))?, vec![json!(1)]);
+        assert_eq!(run(".[0].Name", players())?, vec![json!("Buffon")]);
+        assert_eq!(run(".[-1].\"Kit Number\"", players())?, vec![json!(7)]);
+        assert_eq!(run(".[1:].[].Name", players())?.len(), 2);
+        assert_eq!(run(".a.b", json!({"a": null}))?, vec![Value::Null]);
+        assert!(run(".a", json!([1])).is_err());
+        assert_eq!(run(".a?", json!([1]))?, Vec::<Value>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn test_select_map_and_construct() -> Result<()> {
+        let ret = run(
+            r#"map(select(."Kit Number" < 20 and .Position != "Goalkeeper") | {Name, kit: ."Kit Number"})"#,
+            players(),
+        )?;
+        assert_eq!(
+            ret,
+            vec![json!([{"Name": "Dybala", "kit": 10}, {"Name": "Ronaldo", "kit": 7}])]
+        );
+        assert_eq!(
+            run("[.[].\"Kit Number\"] | add, length", players())?,
+            vec![json!(94), json!(3)]
+        );
+        assert_eq!(
+            run("sort_by(.Name) | reverse | first.Name", players())?,
+            vec![json!("Ronaldo")]
+        );
+        assert_eq!(
+            run(".[] | select(.Name == \"Dybala\") | keys", players())?.len(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_filters() {
+        for filter in [".[", "map(", "{a:}", "foo", ". ==", ".a |"] {
+            assert!(run(filter, json!({})).is_err(), "{} should fail", filter);
+        }
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let mut value = json!({"b": 1, "a": {"d": 2, "c": [{"f": 3, "e": 4}]}});
+        sort_keys(&mut value);
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"a":{"c":[{"e":4,"f":3}],"d":2},"b":1}"#
+        );
+    }
+}
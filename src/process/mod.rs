@@ -1,11 +1,54 @@
 mod base64;
+mod convert;
+mod csv_chart;
 mod csv_convert;
+mod csv_fake;
+mod csv_fill;
+mod csv_index;
+mod csv_mask;
+mod csv_parallel;
+mod csv_reshape;
+mod csv_sample;
+mod csv_transform;
+mod fixed_width;
 mod gen_pass;
+mod geojson;
 mod http_serve;
+mod json_query;
+mod otp;
+mod pass_pattern;
+mod password_breach;
+mod password_check;
+mod schema_infer;
+mod spreadsheet;
 mod text;
 
 pub use base64::{process_decode, process_encode};
-pub use csv_convert::process_csv;
-pub use gen_pass::process_genpass;
+pub use convert::*;
+pub use csv_chart::*;
+pub use csv_convert::*;
+pub use csv_fake::*;
+pub use csv_fill::*;
+pub use csv_index::*;
+pub use csv_mask::*;
+pub use csv_parallel::*;
+pub use csv_reshape::*;
+pub use csv_sample::*;
+pub use csv_transform::*;
+pub use fixed_width::*;
+pub use gen_pass::{
+    derive_password, format_passwords, generate_passphrase, generate_password, generate_strong,
+    master_key, passphrase_entropy, password_entropy, process_genpass, CharClass,
+    GeneratedPassword, PassphraseSpec, PasswordPolicy, PasswordSource, PasswordStrength,
+    StrengthTarget, Wordlist, AMBIGUOUS, MAX_PASSWORD_LENGTH,
+};
+pub use geojson::*;
 pub use http_serve::*;
+pub use json_query::*;
+pub use otp::*;
+pub use pass_pattern::*;
+pub use password_breach::*;
+pub use password_check::*;
+pub use schema_infer::*;
+pub use spreadsheet::*;
 pub use text::*;
@@ -0,0 +1,370 @@
+use crate::OtpAlgorithm;
+use anyhow::Result;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use qrcode::render::unicode::Dense1x2;
+use qrcode::QrCode;
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+// Steps or counters verify may look past; a wider window accepts ever more guesses
+pub const MAX_OTP_WINDOW: u64 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtpParams {
+    pub algorithm: OtpAlgorithm,
+    pub digits: u32,
+    // seconds per TOTP step
+    pub period: u64,
+    // HOTP when set, holding the counter of the next code; TOTP otherwise
+    pub counter: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtpKey {
+    pub secret: Vec<u8>,
+    pub params: OtpParams,
+}
+
+impl Default for OtpParams {
+    // what authenticator apps assume when the URI leaves a parameter out
+    fn default() -> Self {
+        Self {
+            algorithm: OtpAlgorithm::Sha1,
+            digits: 6,
+            period: 30,
+            counter: None,
+        }
+    }
+}
+
+impl OtpKey {
+    // A base32 secret, or an otpauth:// URI that also carries the parameters
+    pub fn parse(content: &str) -> Result<Self> {
+        let content = content.trim();
+        if !content.starts_with("otpauth://") {
+            return Ok(Self {
+                secret: decode_secret(content)?,
+                params: OtpParams::default(),
+            });
+        }
+        let rest = &content["otpauth://".len()..];
+        let (kind, rest) = rest
+            .split_once('/')
+            .ok_or_else(|| anyhow::anyhow!("otpauth URI has no type"))?;
+        let query = rest.split_once('?').map(|(_, q)| q).unwrap_or_default();
+        let mut params = OtpParams::default();
+        let mut secret = None;
+        for (name, value) in query.split('&').filter_map(|p| p.split_once('=')) {
+            let value = percent_decode(value)?;
+            match name.to_lowercase().as_str() {
+                "secret" => secret = Some(decode_secret(&value)?),
+                "algorithm" => params.algorithm = value.parse()?,
+                "digits" => params.digits = value.parse()?,
+                "period" => params.period = value.parse()?,
+                "counter" => params.counter = Some(value.parse()?),
+                _ => {}
+            }
+        }
+        match kind.to_lowercase().as_str() {
+            "totp" => params.counter = None,
+            "hotp" => params.counter = Some(params.counter.unwrap_or(0)),
+            v => anyhow::bail!("unsupported otpauth type {}", v),
+        }
+        let secret = secret.ok_or_else(|| anyhow::anyhow!("otpauth URI has no secret"))?;
+        Ok(Self { secret, params })
+    }
+
+    // The otpauth:// URI authenticator apps import, as described by Google Authenticator's Key Uri Format
+    pub fn uri(&self, issuer: Option<&str>, account: &str) -> String {
+        let p = &self.params;
+        let label = match issuer {
+            Some(issuer) => format!("{}:{}", percent_encode(issuer), percent_encode(account)),
+            None => percent_encode(account),
+        };
+        let kind = if p.counter.is_some() { "hotp" } else { "totp" };
+        let mut uri = format!(
+            "otpauth://{}/{}?secret={}&algorithm={}&digits={}",
+            kind,
+            label,
+            BASE32_NOPAD.encode(&self.secret),
+            p.algorithm.to_string().to_uppercase(),
+            p.digits
+        );
+        match p.counter {
+            Some(counter) => uri.push_str(&format!("&counter={}", counter)),
+            None => uri.push_str(&format!("&period={}", p.period)),
+        }
+        if let Some(issuer) = issuer {
+            uri.push_str(&format!("&issuer={}", percent_encode(issuer)));
+        }
+        uri
+    }
+
+    // The code for the given unix time, or for the counter under HOTP
+    pub fn code(&self, unix_time: u64) -> Result<String> {
+        let counter = match self.params.counter {
+            Some(counter) => counter,
+            None => unix_time / self.period()?,
+        };
+        hotp(
+            &self.secret,
+            counter,
+            self.params.algorithm,
+            self.params.digits,
+        )
+    }
+
+    // The offset from the current step (TOTP) or counter (HOTP) of the code that matches;
+    // TOTP looks window steps either way for clock drift, HOTP only ahead, for presses never sent
+    pub fn verify(&self, code: &str, unix_time: u64, window: u64) -> Result<Option<i64>> {
+        if window > MAX_OTP_WINDOW {
+            anyhow::bail!("window {} is above the limit of {}", window, MAX_OTP_WINDOW)
+        }
+        let code = code.trim();
+        let (base, lo) = match self.params.counter {
+            Some(counter) => (counter, 0),
+            None => {
+                let step = unix_time / self.period()?;
+                (step, window.min(step))
+            }
+        };
+        for offset in -(lo as i64)..=window as i64 {
+            let counter = base
+                .checked_add_signed(offset)
+                .ok_or_else(|| anyhow::anyhow!("counter {} has no codes after it", base))?;
+            let expected = hotp(
+                &self.secret,
+                counter,
+                self.params.algorithm,
+                self.params.digits,
+            )?;
+            if constant_time_eq(expected.as_bytes(), code.as_bytes()) {
+                return Ok(Some(offset));
+            }
+        }
+        Ok(None)
+    }
+
+    fn period(&self) -> Result<u64> {
+        match self.params.period {
+            0 => anyhow::bail!("TOTP period must be at least one second"),
+            period => Ok(period),
+        }
+    }
+}
+
+// RFC 4226: a dynamically truncated HMAC of the big-endian counter
+pub fn hotp(secret: &[u8], counter: u64, algorithm: OtpAlgorithm, digits: u32) -> Result<String> {
+    if !(6..=8).contains(&digits) {
+        anyhow::bail!("OTP codes have 6 to 8 digits, not {}", digits)
+    }
+    let message = counter.to_be_bytes();
+    let mac = match algorithm {
+        OtpAlgorithm::Sha1 => hmac::<Hmac<Sha1>>(secret, &message),
+        OtpAlgorithm::Sha256 => hmac::<Hmac<Sha256>>(secret, &message),
+        OtpAlgorithm::Sha512 => hmac::<Hmac<Sha512>>(secret, &message),
+    };
+    let offset = (mac[mac.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes(mac[offset..offset + 4].try_into()?) & 0x7fff_ffff;
+    let code = binary as u64 % 10u64.pow(digits);
+    Ok(format!("{:0width$}", code, width = digits as usize))
+}
+
+fn hmac<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(key).expect("HMAC takes any key");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+// Random secret bytes, drawn like process_genpass from the thread's CSPRNG
+pub fn generate_otp_secret(bytes: usize) -> Vec<u8> {
+    let mut secret = vec![0; bytes];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+pub fn encode_otp_secret(secret: &[u8]) -> String {
+    BASE32_NOPAD.encode(secret)
+}
+
+// Apps show secrets in lowercase groups with spaces and padding, all of which are accepted
+fn decode_secret(secret: &str) -> Result<Vec<u8>> {
+    let secret = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '=')
+        .collect::<String>()
+        .to_uppercase();
+    let secret = BASE32_NOPAD
+        .decode(secret.as_bytes())
+        .map_err(|e| anyhow::anyhow!("invalid base32 secret: {}", e))?;
+    if secret.is_empty() {
+        anyhow::bail!("OTP secret is empty")
+    }
+    Ok(secret)
+}
+
+// Two rows of modules per line of half blocks, drawn light on dark for terminal backgrounds
+pub fn render_qr(text: &str) -> Result<String> {
+    let code = QrCode::new(text.as_bytes())?;
+    Ok(code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> Result<String> {
+    let bytes = s.as_bytes();
+    let mut ret = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = s
+                    .get(i + 1..i + 3)
+                    .ok_or_else(|| anyhow::anyhow!("unfinished escape in {}", s))?;
+                ret.push(u8::from_str_radix(hex, 16)?);
+                i += 3;
+            }
+            b'+' => {
+                ret.push(b' ');
+                i += 1;
+            }
+            b => {
+                ret.push(b);
+                i += 1;
+            }
+        }
+    }
+    Ok(String::from_utf8(ret)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6238 appendix B seeds the three hashes with different key lengths
+    fn rfc6238_key(algorithm: OtpAlgorithm) -> OtpKey {
+        let seed = b"1234567890".repeat(7);
+        let len = match algorithm {
+            OtpAlgorithm::Sha1 => 20,
+            OtpAlgorithm::Sha256 => 32,
+            OtpAlgorithm::Sha512 => 64,
+        };
+        OtpKey {
+            secret: seed[..len].to_vec(),
+            params: OtpParams {
+                algorithm,
+                digits: 8,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_hotp_rfc4226() -> Result<()> {
+        let codes = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+        for (counter, code) in codes.into_iter().enumerate() {
+            assert_eq!(
+                hotp(
+                    b"12345678901234567890",
+                    counter as u64,
+                    OtpAlgorithm::Sha1,
+                    6
+                )?,
+                code
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_totp_rfc6238() -> Result<()> {
+        let vectors = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for (time, sha1, sha256, sha512) in vectors {
+            assert_eq!(rfc6238_key(OtpAlgorithm::Sha1).code(time)?, sha1);
+            assert_eq!(rfc6238_key(OtpAlgorithm::Sha256).code(time)?, sha256);
+            assert_eq!(rfc6238_key(OtpAlgorithm::Sha512).code(time)?, sha512);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_otp_verify() -> Result<()> {
+        let key = rfc6238_key(OtpAlgorithm::Sha1);
+        // the code for 59 is still accepted a step later, and a step early
+        assert_eq!(key.verify("94287082", 89, 1)?, Some(-1));
+        assert_eq!(key.verify("94287082", 29, 1)?, Some(1));
+        assert_eq!(key.verify("94287082", 119, 1)?, None);
+        assert_eq!(key.verify("94287082", 89, 0)?, None);
+        assert_eq!(key.verify("9428708", 59, 1)?, None);
+        assert!(key.verify("94287082", 59, u64::MAX).is_err());
+
+        let mut key = key;
+        key.params = OtpParams {
+            counter: Some(3),
+            ..Default::default()
+        };
+        key.secret = b"12345678901234567890".to_vec();
+        assert_eq!(key.verify("338314", 0, 2)?, Some(1));
+        assert_eq!(key.verify("359152", 0, 2)?, None);
+        key.params.counter = Some(u64::MAX);
+        assert!(key.verify("359152", 0, 1).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_otp_uri() -> Result<()> {
+        let key = OtpKey {
+            secret: b"12345678901234567890".to_vec(),
+            params: OtpParams::default(),
+        };
+        let uri = key.uri(Some("Acme Co"), "alice@example.com");
+        assert_eq!(
+            uri,
+            "otpauth://totp/Acme%20Co:alice%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ\
+             &algorithm=SHA1&digits=6&period=30&issuer=Acme%20Co"
+        );
+        assert_eq!(OtpKey::parse(&uri)?, key);
+
+        let hotp = OtpKey::parse("otpauth://hotp/x?secret=gezd gnbv gy3t qojq&digits=8&counter=5")?;
+        assert_eq!(hotp.secret, b"1234567890");
+        assert_eq!(hotp.params.counter, Some(5));
+        assert_eq!(hotp.params.digits, 8);
+        assert_eq!(
+            OtpKey::parse("GEZDGNBVGY3TQOJQ")?.params,
+            OtpParams::default()
+        );
+        assert!(OtpKey::parse("otpauth://totp/x?digits=6").is_err());
+        assert!(OtpKey::parse("not base32!").is_err());
+        assert!(render_qr(&uri)?.lines().count() > 20);
+        Ok(())
+    }
+}
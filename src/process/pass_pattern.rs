@@ -0,0 +1,276 @@
+use crate::{CharClass, PasswordPolicy, MAX_PASSWORD_LENGTH};
+use anyhow::Result;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::str::FromStr;
+
+const VOWELS: &str = "aeiouAEIOU";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Placeholder {
+    Literal(char),
+    Consonant { upper: bool },
+    Vowel { upper: bool },
+    Digit,
+    Symbol,
+    Any,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PatternToken {
+    placeholder: Placeholder,
+    min: usize,
+    max: usize,
+}
+
+// C/c consonant, V/v vowel, 9 digit, ! symbol, * any character of the policy,
+// {n} or {n,m} repeats the previous token, \ escapes, anything else is literal
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordPattern(Vec<PatternToken>);
+
+impl Placeholder {
+    fn chars(self, policy: &PasswordPolicy) -> Result<Vec<char>> {
+        let letters = |upper: bool| {
+            let class = if upper {
+                CharClass::Upper
+            } else {
+                CharClass::Lower
+            };
+            policy.class_chars(class)
+        };
+        let chars = match self {
+            Placeholder::Literal(c) => vec![c],
+            Placeholder::Consonant { upper } => letters(upper)
+                .into_iter()
+                .filter(|c| c.is_ascii_alphabetic() && !VOWELS.contains(*c))
+                .collect(),
+            Placeholder::Vowel { upper } => letters(upper)
+                .into_iter()
+                .filter(|c| VOWELS.contains(*c))
+                .collect(),
+            Placeholder::Digit => policy.class_chars(CharClass::Number),
+            Placeholder::Symbol => policy.class_chars(CharClass::Symbol),
+            Placeholder::Any => policy.alphabet(),
+        };
+        // a charset narrows every placeholder, not only *
+        let chars = match (&policy.charset, self) {
+            (Some(_), Placeholder::Literal(_)) | (None, _) => chars,
+            (Some(_), _) => {
+                let alphabet = policy.alphabet();
+                chars.into_iter().filter(|c| alphabet.contains(c)).collect()
+            }
+        };
+        if chars.is_empty() {
+            anyhow::bail!("no characters left for {} in the pattern", self)
+        }
+        Ok(chars)
+    }
+}
+
+impl PasswordPattern {
+    // A repeated token draws its count in proportion to how many strings each count gives,
+    // so every string the token can produce is equally likely
+    pub fn generate(&self, mut rng: &mut impl Rng, policy: &PasswordPolicy) -> Result<String> {
+        let mut password = String::new();
+        for token in &self.0 {
+            let chars = token.placeholder.chars(policy)?;
+            let ln_size = (chars.len() as f64).ln();
+            let ln_total = ln_sum(token.min, token.max, ln_size);
+            let mut target = rng.gen::<f64>();
+            let mut count = token.max;
+            for k in token.min..=token.max {
+                target -= (k as f64 * ln_size - ln_total).exp();
+                if target <= 0.0 {
+                    count = k;
+                    break;
+                }
+            }
+            for _ in 0..count {
+                password.push(*chars.choose(&mut rng).expect("chars won't be empty"));
+            }
+        }
+        Ok(password)
+    }
+
+    // Exact unless neighbouring repeated tokens can produce the same text in different ways
+    pub fn entropy(&self, policy: &PasswordPolicy) -> Result<f64> {
+        let mut bits = 0.0;
+        for token in &self.0 {
+            let ln_size = (token.placeholder.chars(policy)?.len() as f64).ln();
+            bits += ln_sum(token.min, token.max, ln_size) / std::f64::consts::LN_2;
+        }
+        Ok(bits)
+    }
+}
+
+// ln of size^min + ... + size^max
+fn ln_sum(min: usize, max: usize, ln_size: f64) -> f64 {
+    let top = max as f64 * ln_size;
+    let sum = (min..=max)
+        .map(|k| (k as f64 * ln_size - top).exp())
+        .sum::<f64>();
+    top + sum.ln()
+}
+
+fn parse_repeat(spec: &str) -> Result<(usize, usize)> {
+    let number = |s: &str| {
+        s.trim()
+            .parse::<usize>()
+            .map_err(|_| anyhow::anyhow!("invalid repeat count {{{}}} in pattern", spec))
+    };
+    let (min, max) = match spec.split_once(',') {
+        Some((min, max)) => (number(min)?, number(max)?),
+        None => (number(spec)?, number(spec)?),
+    };
+    if min > max {
+        anyhow::bail!("repeat {{{}}} has its minimum above its maximum", spec)
+    }
+    if max > MAX_PASSWORD_LENGTH {
+        anyhow::bail!(
+            "repeat {{{}}} is above the {} character limit",
+            spec,
+            MAX_PASSWORD_LENGTH
+        )
+    }
+    Ok((min, max))
+}
+
+impl FromStr for PasswordPattern {
+    type Err = anyhow::Error;
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let mut tokens: Vec<PatternToken> = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            if c == '{' {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => anyhow::bail!("pattern has an unclosed {{{}", spec),
+                    }
+                }
+                let token = tokens
+                    .last_mut()
+                    .ok_or_else(|| anyhow::anyhow!("repeat {{{}}} has nothing to repeat", spec))?;
+                if token.min != 1 || token.max != 1 {
+                    anyhow::bail!("repeat {{{}}} follows another repeat", spec)
+                }
+                (token.min, token.max) = parse_repeat(&spec)?;
+                continue;
+            }
+            let placeholder = match c {
+                'C' => Placeholder::Consonant { upper: true },
+                'c' => Placeholder::Consonant { upper: false },
+                'V' => Placeholder::Vowel { upper: true },
+                'v' => Placeholder::Vowel { upper: false },
+                '9' => Placeholder::Digit,
+                '!' => Placeholder::Symbol,
+                '*' => Placeholder::Any,
+                '\\' => match chars.next() {
+                    Some(c) => Placeholder::Literal(c),
+                    None => anyhow::bail!("pattern ends with an unfinished escape"),
+                },
+                c => Placeholder::Literal(c),
+            };
+            tokens.push(PatternToken {
+                placeholder,
+                min: 1,
+                max: 1,
+            });
+        }
+        if tokens.is_empty() {
+            anyhow::bail!("pattern is empty")
+        }
+        let longest = tokens.iter().map(|token| token.max).sum::<usize>();
+        if longest > MAX_PASSWORD_LENGTH {
+            anyhow::bail!(
+                "pattern can give {} characters, above the {} character limit",
+                longest,
+                MAX_PASSWORD_LENGTH
+            )
+        }
+        Ok(Self(tokens))
+    }
+}
+
+impl std::fmt::Display for Placeholder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Placeholder::Literal(c) => write!(f, "{:?}", c),
+            Placeholder::Consonant { upper: true } => write!(f, "C"),
+            Placeholder::Consonant { upper: false } => write!(f, "c"),
+            Placeholder::Vowel { upper: true } => write!(f, "V"),
+            Placeholder::Vowel { upper: false } => write!(f, "v"),
+            Placeholder::Digit => write!(f, "9"),
+            Placeholder::Symbol => write!(f, "!"),
+            Placeholder::Any => write!(f, "*"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use regex::Regex;
+
+    #[test]
+    fn test_pattern() -> Result<()> {
+        let pattern: PasswordPattern = r"Cvcv-9999-!!\9{2}c{1,3}".parse()?;
+        let policy = PasswordPolicy::default();
+        let re = Regex::new(r"^[B-Z][aeiou][b-z][aeiou]-[1-9]{4}-[!@#$%^&*_]{2}99[b-z]{1,3}$")?;
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut lengths = [0; 3];
+        for _ in 0..1000 {
+            let password = pattern.generate(&mut rng, &policy)?;
+            assert!(re.is_match(&password), "{}", password);
+            lengths[password.len() - 15] += 1;
+        }
+        // 20^3 of the 20 + 20^2 + 20^3 consonant tails are three long (l is ambiguous)
+        assert!(lengths[2] > 900);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pattern_entropy() -> Result<()> {
+        let policy = PasswordPolicy::default();
+        let pattern: PasswordPattern = "9{2}-!".parse()?;
+        let bits = 2.0 * 9f64.log2() + 9f64.log2();
+        assert!((pattern.entropy(&policy)? - bits).abs() < 1e-9);
+        let pattern: PasswordPattern = "x{0,1}".parse()?;
+        assert!((pattern.entropy(&policy)? - 1.0).abs() < 1e-9);
+
+        let invalid = [
+            "",
+            "{2}",
+            "9{3,1}",
+            "9{a}",
+            "9{2}{3}",
+            "9{2",
+            "ab\\",
+            "9{0,99999999999}",
+            "9{4097}",
+            "9{4000}!{100}",
+        ];
+        for invalid in invalid {
+            assert!(invalid.parse::<PasswordPattern>().is_err(), "{}", invalid);
+        }
+        let policy = PasswordPolicy {
+            exclude: "123456789".into(),
+            ..Default::default()
+        };
+        assert!("9".parse::<PasswordPattern>()?.entropy(&policy).is_err());
+
+        // a charset applies to the classes as well as to *
+        let policy = PasswordPolicy {
+            charset: Some("ab12".into()),
+            ..Default::default()
+        };
+        let pattern: PasswordPattern = "c9*".parse()?;
+        assert!((pattern.entropy(&policy)? - 3.0).abs() < 1e-9);
+        assert!("C".parse::<PasswordPattern>()?.entropy(&policy).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use crate::{generate_strong, BreachHash, PasswordFormat, PasswordSource, StrengthTarget};
+use anyhow::Result;
+use md4::Md4;
+use rand::Rng;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+
+// A Have I Been Pwned download: "HASH:COUNT" lines in hash order, as produced by
+// the PwnedPasswordsDownloader. The file is never loaded, lookups seek through it.
+#[derive(Debug)]
+pub struct BreachList {
+    file: File,
+    len: u64,
+    hash: BreachHash,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BreachReport {
+    pub password: String,
+    // how often the password shows up across known breaches, 0 when it doesn't
+    pub count: u64,
+}
+
+impl BreachList {
+    // Without a hash type, it is told by the length of the first hash in the file
+    pub fn open(path: &str, hash: Option<BreachHash>) -> Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mut first = String::new();
+        BufReader::new(&file).read_line(&mut first)?;
+        let prefix = first.split(':').next().unwrap_or_default().trim();
+        let detected = match prefix.len() {
+            40 => Some(BreachHash::Sha1),
+            32 => Some(BreachHash::Ntlm),
+            _ => None,
+        };
+        let Some(detected) = detected.filter(|_| prefix.bytes().all(|b| b.is_ascii_hexdigit()))
+        else {
+            anyhow::bail!(
+                "{} does not look like a Have I Been Pwned file of HASH:COUNT lines",
+                path
+            )
+        };
+        if hash.is_some_and(|hash| hash != detected) {
+            anyhow::bail!(
+                "{} holds {} hashes, not {}",
+                path,
+                detected,
+                hash.expect("checked above")
+            )
+        }
+        Ok(Self {
+            file,
+            len,
+            hash: detected,
+        })
+    }
+
+    pub fn hash(&self, password: &str) -> String {
+        let digest = match self.hash {
+            BreachHash::Sha1 => Sha1::digest(password.as_bytes()).to_vec(),
+            // NTLM is MD4 over the UTF-16LE encoding
+            BreachHash::Ntlm => {
+                let utf16 = password
+                    .encode_utf16()
+                    .flat_map(|u| u.to_le_bytes())
+                    .collect::<Vec<_>>();
+                Md4::digest(utf16).to_vec()
+            }
+        };
+        digest.iter().map(|b| format!("{:02X}", b)).collect()
+    }
+
+    // How many times the password was seen in breaches
+    pub fn count(&self, password: &str) -> Result<u64> {
+        self.lookup(&self.hash(password))
+    }
+
+    // Binary search over byte offsets, landing on the first line not below the target
+    fn lookup(&self, target: &str) -> Result<u64> {
+        let mut reader = BufReader::new(&self.file);
+        let (mut lo, mut hi) = (0, self.len);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match line_from(&mut reader, mid)? {
+                Some(line) if compare(&line, target) == Ordering::Less => lo = mid + 1,
+                _ => hi = mid,
+            }
+        }
+        let Some(line) = line_from(&mut reader, lo)? else {
+            return Ok(0);
+        };
+        if compare(&line, target) != Ordering::Equal {
+            return Ok(0);
+        }
+        let count = line.split(':').nth(1).unwrap_or("1").trim();
+        count
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid count in breach line {}", line.trim()))
+    }
+}
+
+// The first whole line starting at or after pos
+fn line_from(reader: &mut BufReader<&File>, pos: u64) -> Result<Option<String>> {
+    let mut line = String::new();
+    if pos > 0 {
+        // the byte before pos is either the end of the previous line, or inside the line to skip
+        reader.seek(SeekFrom::Start(pos - 1))?;
+        reader.read_line(&mut line)?;
+        line.clear();
+    } else {
+        reader.seek(SeekFrom::Start(0))?;
+    }
+    Ok((reader.read_line(&mut line)? > 0).then_some(line))
+}
+
+fn compare(line: &str, target: &str) -> Ordering {
+    let hash = line.split(':').next().unwrap_or_default().trim();
+    hash.to_ascii_uppercase().as_str().cmp(target)
+}
+
+// Generates as generate_strong does, but draws again whenever the password appears
+// in a breach, as one that has can never be issued
+pub fn generate_unbreached(
+    rng: &mut impl Rng,
+    source: &mut PasswordSource,
+    target: &StrengthTarget,
+    list: &BreachList,
+) -> Result<String> {
+    const ATTEMPTS: usize = 100;
+    for _ in 0..ATTEMPTS {
+        let password = generate_strong(rng, source, target)?;
+        if list.count(&password)? == 0 {
+            return Ok(password);
+        }
+    }
+    anyhow::bail!(
+        "all of {} generated passwords appear in known breaches, allow a longer or richer password",
+        ATTEMPTS
+    )
+}
+
+// Candidates come one per line, as for password check
+pub fn process_password_breached(content: &str, list: &BreachList) -> Result<Vec<BreachReport>> {
+    content
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .filter(|line| !line.is_empty())
+        .map(|password| {
+            Ok(BreachReport {
+                password: password.into(),
+                count: list.count(password)?,
+            })
+        })
+        .collect()
+}
+
+pub fn format_breach_reports(reports: &[BreachReport], format: PasswordFormat) -> Result<String> {
+    let ret = match format {
+        PasswordFormat::Plain => reports
+            .iter()
+            .map(|r| match r.count {
+                0 => format!("{}\tnot found\n", r.password),
+                n => format!("{}\tbreached {} times\n", r.password, n),
+            })
+            .collect(),
+        PasswordFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record(["password", "count"])?;
+            for r in reports {
+                writer.write_record([r.password.clone(), r.count.to_string()])?;
+            }
+            String::from_utf8(writer.into_inner()?)?
+        }
+        PasswordFormat::Json => serde_json::to_string_pretty(reports)? + "\n",
+    };
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PasswordPolicy;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_breach_list_sha1() -> Result<()> {
+        let list = BreachList::open("fixtures/hibp_sha1.txt", None)?;
+        assert_eq!(
+            list.hash("password"),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+        assert_eq!(list.count("password")?, 9545824);
+        assert_eq!(list.count("123456")?, 37359195);
+        assert_eq!(list.count("correct horse battery staple")?, 3);
+        assert_eq!(list.count("Password")?, 0);
+        assert_eq!(list.count("k#V9p!xq2Lm$wR7z")?, 0);
+
+        // every hash in the file is found, the first and last lines included
+        let content = std::fs::read_to_string("fixtures/hibp_sha1.txt")?;
+        for line in content.lines() {
+            let (hash, count) = line.split_once(':').unwrap();
+            assert_eq!(list.lookup(hash)?, count.trim().parse::<u64>()?);
+            // and one that differs in the last digit is not
+            let last = if hash.ends_with('0') { '1' } else { '0' };
+            assert_eq!(list.lookup(&format!("{}{}", &hash[..39], last))?, 0);
+        }
+        assert!(BreachList::open("fixtures/hibp_sha1.txt", Some(BreachHash::Ntlm)).is_err());
+        assert!(BreachList::open("fixtures/file.txt", None).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_breach_list_ntlm() -> Result<()> {
+        let list = BreachList::open("fixtures/hibp_ntlm.txt", None)?;
+        assert_eq!(list.hash("password"), "8846F7EAEE8FB117AD06BDD830B7586C");
+        assert_eq!(list.count("password")?, 9545824);
+        assert_eq!(list.count("hunter2")?, 17043);
+        assert_eq!(list.count("hunter3")?, 0);
+
+        let reports = process_password_breached("password\r\n\nhunter3\n", &list)?;
+        let plain = format_breach_reports(&reports, PasswordFormat::Plain)?;
+        assert_eq!(
+            plain,
+            "password\tbreached 9545824 times\nhunter3\tnot found\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_unbreached() -> Result<()> {
+        let list = BreachList::open("fixtures/hibp_sha1.txt", None)?;
+        let mut rng = StdRng::seed_from_u64(17);
+        let target = StrengthTarget::default();
+        // half of what the pattern makes is "password", which is always drawn again
+        let mut source =
+            PasswordSource::Pattern("password{0,1}".parse()?, PasswordPolicy::default());
+        for _ in 0..20 {
+            let password = generate_unbreached(&mut rng, &mut source, &target, &list)?;
+            assert_eq!(password, "passwor");
+        }
+        let mut source = PasswordSource::Pattern("123456".parse()?, PasswordPolicy::default());
+        assert!(generate_unbreached(&mut rng, &mut source, &target, &list).is_err());
+        Ok(())
+    }
+}
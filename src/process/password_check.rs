@@ -0,0 +1,242 @@
+use crate::PasswordFormat;
+use anyhow::Result;
+use serde::Serialize;
+use zxcvbn::matching::patterns::MatchPattern;
+use zxcvbn::Match;
+
+#[derive(Debug, Serialize)]
+pub struct PasswordReport {
+    pub password: String,
+    pub score: u8,
+    pub guesses: u64,
+    pub guesses_log10: f64,
+    pub crack_times: CrackTimes,
+    // the cheapest part of the password to guess, as zxcvbn split it up
+    pub weakest_pattern: String,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrackTimes {
+    // an online attack against a service that rate limits, 100 guesses per hour
+    pub online_throttled: String,
+    // an online attack without rate limiting, 10 guesses per second
+    pub online_unthrottled: String,
+    // a stolen hash from a slow function like bcrypt, 10k guesses per second
+    pub offline_slow_hash: String,
+    // a stolen hash from a fast function like SHA-1, 10 billion guesses per second
+    pub offline_fast_hash: String,
+}
+
+// Context words are what an attacker would try first: the username, the company, the site
+pub fn check_password(password: &str, context: &[&str]) -> Result<PasswordReport> {
+    let estimate = zxcvbn::zxcvbn(password, context)?;
+    let times = estimate.crack_times();
+    let weakest = estimate
+        .sequence()
+        .iter()
+        .filter(|m| m.pattern != MatchPattern::BruteForce)
+        .min_by_key(|m| m.guesses.unwrap_or(u64::MAX))
+        .map_or_else(|| "none, only brute force".into(), describe_match);
+    let (warning, suggestions) = match estimate.feedback() {
+        Some(feedback) => (
+            feedback.warning().map(|w| w.to_string()),
+            feedback
+                .suggestions()
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        ),
+        None => (None, Vec::new()),
+    };
+    Ok(PasswordReport {
+        password: password.into(),
+        score: estimate.score(),
+        guesses: estimate.guesses(),
+        guesses_log10: (estimate.guesses_log10() * 100.0).round() / 100.0,
+        crack_times: CrackTimes {
+            online_throttled: times.online_throttling_100_per_hour().to_string(),
+            online_unthrottled: times.online_no_throttling_10_per_second().to_string(),
+            offline_slow_hash: times.offline_slow_hashing_1e4_per_second().to_string(),
+            offline_fast_hash: times.offline_fast_hashing_1e10_per_second().to_string(),
+        },
+        weakest_pattern: weakest,
+        warning,
+        suggestions,
+    })
+}
+
+// Candidates come one per line; blank lines are skipped, as zxcvbn has nothing to rate
+pub fn process_password_check(
+    content: &str,
+    context: &[String],
+    format: PasswordFormat,
+) -> Result<String> {
+    let context = context.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let reports = content
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .filter(|line| !line.is_empty())
+        .map(|password| check_password(password, &context))
+        .collect::<Result<Vec<_>>>()?;
+    format_reports(&reports, format)
+}
+
+fn describe_match(m: &Match) -> String {
+    let token = &m.token;
+    match &m.pattern {
+        MatchPattern::Dictionary(d) => {
+            let mut ret = format!(
+                "\"{}\" is word {} of the {} list",
+                token,
+                d.rank,
+                dictionary_name(&format!("{:?}", d.dictionary_name))
+            );
+            if d.reversed {
+                ret.push_str(", reversed");
+            }
+            if let Some(sub) = &d.sub_display {
+                ret.push_str(&format!(", with substitutions {}", sub));
+            }
+            ret
+        }
+        MatchPattern::Spatial(s) => format!(
+            "\"{}\" is a {} keyboard pattern with {} turns",
+            token, s.graph, s.turns
+        ),
+        MatchPattern::Repeat(r) => format!(
+            "\"{}\" repeats \"{}\" {} times",
+            token, r.base_token, r.repeat_count
+        ),
+        MatchPattern::Sequence(s) => format!("\"{}\" is a {} sequence", token, s.sequence_name),
+        MatchPattern::Regex(r) => format!("\"{}\" is a {}", token, r.regex_name.replace('_', " ")),
+        MatchPattern::Date(_) => format!("\"{}\" is a date", token),
+        MatchPattern::BruteForce => format!("\"{}\" needs brute force", token),
+    }
+}
+
+// zxcvbn keeps its dictionary type private, so go by its Debug name
+fn dictionary_name(name: &str) -> &str {
+    match name {
+        "Passwords" => "common passwords",
+        "English" => "English words",
+        "FemaleNames" => "female names",
+        "MaleNames" => "male names",
+        "Surnames" => "surnames",
+        "UsTvAndFilm" => "TV and film words",
+        "UserInputs" => "context words",
+        name => name,
+    }
+}
+
+pub fn format_reports(reports: &[PasswordReport], format: PasswordFormat) -> Result<String> {
+    let ret = match format {
+        PasswordFormat::Plain => {
+            let mut ret = String::new();
+            for r in reports {
+                let t = &r.crack_times;
+                ret.push_str(&format!(
+                    "{}\n  score: {}/4\n  guesses: {} (10^{:.2})\n  crack time:\n",
+                    r.password, r.score, r.guesses, r.guesses_log10
+                ));
+                for (attack, time) in [
+                    ("online, throttled (100/hour)", &t.online_throttled),
+                    ("online, unthrottled (10/s)", &t.online_unthrottled),
+                    ("offline, slow hash (1e4/s)", &t.offline_slow_hash),
+                    ("offline, fast hash (1e10/s)", &t.offline_fast_hash),
+                ] {
+                    ret.push_str(&format!("    {}: {}\n", attack, time));
+                }
+                ret.push_str(&format!("  weakest pattern: {}\n", r.weakest_pattern));
+                if let Some(warning) = &r.warning {
+                    ret.push_str(&format!("  warning: {}\n", warning));
+                }
+                for suggestion in &r.suggestions {
+                    ret.push_str(&format!("  suggestion: {}\n", suggestion));
+                }
+            }
+            ret
+        }
+        PasswordFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            writer.write_record([
+                "password",
+                "score",
+                "guesses",
+                "guesses_log10",
+                "online_throttled",
+                "online_unthrottled",
+                "offline_slow_hash",
+                "offline_fast_hash",
+                "weakest_pattern",
+                "warning",
+                "suggestions",
+            ])?;
+            for r in reports {
+                let t = &r.crack_times;
+                writer.write_record([
+                    r.password.clone(),
+                    r.score.to_string(),
+                    r.guesses.to_string(),
+                    r.guesses_log10.to_string(),
+                    t.online_throttled.clone(),
+                    t.online_unthrottled.clone(),
+                    t.offline_slow_hash.clone(),
+                    t.offline_fast_hash.clone(),
+                    r.weakest_pattern.clone(),
+                    r.warning.clone().unwrap_or_default(),
+                    r.suggestions.join(" "),
+                ])?;
+            }
+            String::from_utf8(writer.into_inner()?)?
+        }
+        PasswordFormat::Json => serde_json::to_string_pretty(reports)? + "\n",
+    };
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_password() -> Result<()> {
+        let report = check_password("password1", &[])?;
+        assert_eq!(report.score, 0);
+        assert!(report.weakest_pattern.contains("common passwords"));
+        assert!(!report.suggestions.is_empty());
+
+        // the username alone is barely better than nothing once zxcvbn knows it
+        let plain = check_password("quentinblake77", &[])?;
+        let known = check_password("quentinblake77", &["quentinblake"])?;
+        assert!(known.guesses < plain.guesses);
+        assert!(known.weakest_pattern.contains("context words"));
+
+        let strong = check_password("k#V9p!xq2Lm$wR7z", &[])?;
+        assert_eq!(strong.score, 4);
+        assert_eq!(strong.weakest_pattern, "none, only brute force");
+        assert!(strong.warning.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_password_check() -> Result<()> {
+        let content = "password1\r\n\nk#V9p!xq2Lm$wR7z\n";
+        let csv = process_password_check(content, &[], PasswordFormat::Csv)?;
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        assert_eq!(reader.headers()?.len(), 11);
+        let rows = reader.records().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][0], "password1");
+
+        let json: serde_json::Value =
+            serde_json::from_str(&process_password_check(content, &[], PasswordFormat::Json)?)?;
+        assert_eq!(json[1]["score"], 4);
+        assert!(json[0]["crack_times"]["offline_fast_hash"].is_string());
+
+        let plain = process_password_check("password1", &[], PasswordFormat::Plain)?;
+        assert!(plain.starts_with("password1\n  score: 0/4\n"));
+        Ok(())
+    }
+}
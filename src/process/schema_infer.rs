@@ -0,0 +1,313 @@
+use crate::TableReader;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate};
+use csv::StringRecord;
+use regex::Regex;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::sync::OnceLock;
+
+const DRAFT_2020_12: &str = "https://json-schema.org/draft/2020-12/schema";
+
+// Accumulates what was observed at one position of the documents
+#[derive(Debug, Default)]
+struct Shape {
+    count: usize,
+    nulls: bool,
+    booleans: bool,
+    integers: bool,
+    numbers: bool,
+    strings: Option<StringStats>,
+    objects: usize,
+    properties: BTreeMap<String, Shape>,
+    // properties are listed in the order they were first seen
+    order: Vec<String>,
+    items: Option<Box<Shape>>,
+}
+
+#[derive(Debug)]
+struct StringStats {
+    count: usize,
+    // None once there are more distinct values than an enum may hold
+    distinct: Option<Vec<String>>,
+    date: bool,
+    date_time: bool,
+    email: bool,
+    uuid: bool,
+}
+
+struct SchemaInferrer {
+    root: Shape,
+    max_enum: usize,
+}
+
+impl SchemaInferrer {
+    fn new(max_enum: usize) -> Self {
+        Self {
+            root: Shape::default(),
+            max_enum,
+        }
+    }
+
+    // Csv cells are typed from their text, and empty cells count as absent
+    fn add_record(&mut self, headers: &StringRecord, record: &StringRecord) {
+        let shape = self.root.items.get_or_insert_with(Default::default);
+        shape.count += 1;
+        shape.objects += 1;
+        for (name, value) in headers.iter().zip(record.iter()) {
+            if !shape.properties.contains_key(name) {
+                shape.order.push(name.to_string());
+            }
+            let property = shape.properties.entry(name.to_string()).or_default();
+            if value.is_empty() {
+                continue;
+            }
+            property.count += 1;
+            if value.parse::<i64>().is_ok() {
+                property.integers = true;
+            } else if value.parse::<f64>().is_ok_and(f64::is_finite) {
+                property.numbers = true;
+            } else if value == "true" || value == "false" {
+                property.booleans = true;
+            } else {
+                property.add_string(value, self.max_enum);
+            }
+        }
+    }
+
+    fn finish(self, title: Option<&str>) -> Value {
+        let mut schema = Map::new();
+        schema.insert("$schema".into(), json!(DRAFT_2020_12));
+        if let Some(title) = title {
+            schema.insert("title".into(), json!(title));
+        }
+        if let Value::Object(inferred) = self.root.to_schema() {
+            schema.extend(inferred);
+        }
+        Value::Object(schema)
+    }
+}
+
+impl Shape {
+    fn add_value(&mut self, value: &Value, max_enum: usize) {
+        self.count += 1;
+        match value {
+            Value::Null => self.nulls = true,
+            Value::Bool(_) => self.booleans = true,
+            Value::Number(n) if n.is_i64() || n.is_u64() => self.integers = true,
+            Value::Number(_) => self.numbers = true,
+            Value::String(s) => self.add_string(s, max_enum),
+            Value::Array(items) => {
+                let shape = self.items.get_or_insert_with(Default::default);
+                for item in items {
+                    shape.add_value(item, max_enum);
+                }
+            }
+            Value::Object(map) => {
+                self.objects += 1;
+                for (key, value) in map {
+                    if !self.properties.contains_key(key) {
+                        self.order.push(key.clone());
+                    }
+                    let property = self.properties.entry(key.clone()).or_default();
+                    property.add_value(value, max_enum);
+                }
+            }
+        }
+    }
+
+    fn add_string(&mut self, value: &str, max_enum: usize) {
+        let stats = self.strings.get_or_insert(StringStats {
+            count: 0,
+            distinct: Some(Vec::new()),
+            date: true,
+            date_time: true,
+            email: true,
+            uuid: true,
+        });
+        stats.count += 1;
+        if let Some(distinct) = &mut stats.distinct {
+            if !distinct.iter().any(|v| v == value) {
+                distinct.push(value.to_string());
+            }
+            if distinct.len() > max_enum {
+                stats.distinct = None;
+            }
+        }
+        stats.date &= value.len() == 10 && NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok();
+        stats.date_time &= DateTime::parse_from_rfc3339(value).is_ok();
+        stats.email &= email_regex().is_match(value);
+        stats.uuid &= uuid_regex().is_match(value);
+    }
+
+    fn to_schema(&self) -> Value {
+        let mut types = Vec::new();
+        let mut schema = Map::new();
+        let mut values = None;
+        if self.objects > 0 {
+            types.push("object");
+            let properties = self
+                .order
+                .iter()
+                .map(|k| (k.clone(), self.properties[k.as_str()].to_schema()))
+                .collect::<Map<_, _>>();
+            let required = self
+                .order
+                .iter()
+                .filter(|k| self.properties[k.as_str()].count == self.objects)
+                .collect::<Vec<_>>();
+            schema.insert("properties".into(), Value::Object(properties));
+            if !required.is_empty() {
+                schema.insert("required".into(), json!(required));
+            }
+        }
+        if let Some(items) = &self.items {
+            types.push("array");
+            schema.insert("items".into(), items.to_schema());
+        }
+        if let Some(stats) = &self.strings {
+            types.push("string");
+            let format = [
+                (stats.uuid, "uuid"),
+                (stats.email, "email"),
+                (stats.date_time, "date-time"),
+                (stats.date, "date"),
+            ]
+            .into_iter()
+            .find_map(|(matched, format)| matched.then_some(format));
+            match (format, &stats.distinct) {
+                (Some(format), _) => {
+                    schema.insert("format".into(), json!(format));
+                }
+                // an enum only documents something when values repeat
+                (None, Some(distinct)) if distinct.len() < stats.count => values = Some(distinct),
+                _ => {}
+            }
+        }
+        match (self.integers, self.numbers) {
+            (_, true) => types.push("number"),
+            (true, false) => types.push("integer"),
+            _ => {}
+        }
+        if self.booleans {
+            types.push("boolean");
+        }
+        if self.nulls {
+            types.push("null");
+        }
+        match types.as_slice() {
+            [] => {}
+            [t] => {
+                schema.insert("type".into(), json!(t));
+                if let Some(values) = values {
+                    schema.insert("enum".into(), json!(values));
+                }
+            }
+            types => {
+                schema.insert("type".into(), json!(types));
+            }
+        }
+        Value::Object(schema)
+    }
+}
+
+fn email_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^[^@\s]+@[^@\s]+\.[^@\s.]+$").expect("valid regex"))
+}
+
+fn uuid_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$")
+            .expect("valid regex")
+    })
+}
+
+pub fn process_schema_infer_csv(
+    reader: Box<dyn TableReader>,
+    max_enum: usize,
+    title: Option<&str>,
+) -> Result<Value> {
+    let (headers, records) = reader.read()?;
+    let mut inferrer = SchemaInferrer::new(max_enum);
+    for record in records {
+        inferrer.add_record(&headers, &record?);
+    }
+    // a csv without rows still documents its columns
+    let items = inferrer.root.items.get_or_insert_with(Default::default);
+    if items.objects == 0 {
+        items.objects = 1;
+        items.order = headers.iter().map(String::from).collect();
+        for name in headers.iter() {
+            items.properties.entry(name.to_string()).or_default();
+        }
+    }
+    Ok(inferrer.finish(title))
+}
+
+// A single array or object is described as is, a stream of values (NDJSON) as an array of them
+pub fn process_schema_infer_json(
+    reader: impl Read,
+    max_enum: usize,
+    title: Option<&str>,
+) -> Result<Value> {
+    let values = serde_json::Deserializer::from_reader(reader)
+        .into_iter::<Value>()
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut inferrer = SchemaInferrer::new(max_enum);
+    match values.as_slice() {
+        [] => anyhow::bail!("no JSON values to infer a schema from"),
+        [value] => inferrer.root.add_value(value, max_enum),
+        values => {
+            let items = inferrer.root.items.get_or_insert_with(Default::default);
+            for value in values {
+                items.add_value(value, max_enum);
+            }
+        }
+    }
+    Ok(inferrer.finish(title))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CsvTableReader;
+
+    #[test]
+    fn test_infer_csv() -> Result<()> {
+        let reader = Box::new(CsvTableReader::try_new("assets/juventus.csv", ',')?);
+        let schema = process_schema_infer_csv(reader, 10, Some("players"))?;
+        assert_eq!(schema["$schema"], DRAFT_2020_12);
+        assert_eq!(schema["type"], "array");
+        let items = &schema["items"];
+        assert_eq!(items["properties"]["Kit Number"]["type"], "integer");
+        assert_eq!(items["properties"]["Name"]["type"], "string");
+        assert!(items["properties"]["Name"].get("enum").is_none());
+        assert_eq!(items["properties"]["Position"]["enum"][0], "Goalkeeper");
+        assert_eq!(items["required"][0], "Name");
+        Ok(())
+    }
+
+    #[test]
+    fn test_infer_ndjson() -> Result<()> {
+        let input = r#"
+            {"id": "0b4c8a1e-8f4e-4c53-9d7b-2a1f0a6b7c11", "email": "a@example.com", "born": "1993-11-15", "score": 1, "tags": ["x"]}
+            {"id": "9d2f7c3a-1b6e-4f0a-8c5d-3e2b1a0f9e22", "email": "b@example.org", "born": "1985-02-05", "score": 2.5, "nick": null}
+        "#;
+        let schema = process_schema_infer_json(input.as_bytes(), 10, None)?;
+        let props = &schema["items"]["properties"];
+        assert_eq!(props["id"]["format"], "uuid");
+        assert_eq!(props["email"]["format"], "email");
+        assert_eq!(props["born"]["format"], "date");
+        assert_eq!(props["score"]["type"], "number");
+        assert_eq!(props["tags"]["items"]["type"], "string");
+        assert_eq!(props["nick"]["type"], "null");
+        assert_eq!(
+            schema["items"]["required"],
+            json!(["id", "email", "born", "score"])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{format_number, Records, TableReader};
+use anyhow::Result;
+use calamine::{open_workbook_auto, Data, Range, Reader};
+use csv::StringRecord;
+use std::path::Path;
+
+const SPREADSHEET_EXTENSIONS: &[&str] = &["xlsx", "xlsm", "xlsb", "xls", "ods"];
+
+pub fn is_spreadsheet(input: &str) -> bool {
+    Path::new(input)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| SPREADSHEET_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+pub struct SpreadsheetReader {
+    input: String,
+    sheet: Option<String>,
+    range: Option<String>,
+}
+
+impl SpreadsheetReader {
+    // sheet is a sheet name or a 0-based index, range is an A1-style reference like B2:E40
+    pub fn new(input: &str, sheet: Option<String>, range: Option<String>) -> Self {
+        Self {
+            input: input.to_string(),
+            sheet,
+            range,
+        }
+    }
+}
+
+impl TableReader for SpreadsheetReader {
+    fn read(self: Box<Self>) -> Result<(StringRecord, Records<'static>)> {
+        let mut workbook = open_workbook_auto(&self.input)?;
+        let names = workbook.sheet_names();
+        let name = match self.sheet {
+            None => names.first(),
+            Some(sheet) => names.iter().find(|name| **name == sheet).or_else(|| {
+                sheet
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| names.get(index))
+            }),
+        }
+        .ok_or_else(|| anyhow::anyhow!("sheet not found, available: {}", names.join(", ")))?
+        .clone();
+        let mut range = workbook.worksheet_range(&name)?;
+        if let Some(reference) = self.range {
+            let (start, end) = parse_range(&reference)?;
+            range = range.range(start, end);
+        }
+        let mut rows = range_to_records(&range).into_iter();
+        let headers = rows.next().unwrap_or_default();
+        Ok((headers, Box::new(rows.map(Ok))))
+    }
+}
+
+fn range_to_records(range: &Range<Data>) -> Vec<StringRecord> {
+    range
+        .rows()
+        .map(|row| row.iter().map(cell_to_string).collect())
+        .collect()
+}
+
+fn cell_to_string(cell: &Data) -> String {
+    match cell {
+        Data::Int(v) => v.to_string(),
+        Data::Float(v) => format_number(*v),
+        Data::String(v) | Data::DateTimeIso(v) | Data::DurationIso(v) => v.clone(),
+        Data::Bool(v) => v.to_string(),
+        Data::DateTime(v) => match v.as_datetime() {
+            Some(dt) if v.is_datetime() && dt.time() == chrono::NaiveTime::MIN => {
+                dt.format("%Y-%m-%d").to_string()
+            }
+            Some(dt) if v.is_datetime() => dt.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            _ => v.as_f64().to_string(),
+        },
+        Data::Error(e) => e.to_string(),
+        Data::Empty => String::new(),
+    }
+}
+
+// Convert an A1-style range like "B2:E40" into 0-based (row, column) corners
+fn parse_range(reference: &str) -> Result<((u32, u32), (u32, u32))> {
+    let (start, end) = reference
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("range must look like A1:D20"))?;
+    let (start, end) = (parse_cell(start)?, parse_cell(end)?);
+    if start.0 > end.0 || start.1 > end.1 {
+        anyhow::bail!(
+            "range {} must run from its top-left to its bottom-right cell",
+            reference
+        );
+    }
+    Ok((start, end))
+}
+
+fn parse_cell(cell: &str) -> Result<(u32, u32)> {
+    let cell = cell.trim().to_uppercase();
+    let split = cell
+        .find(|c: char| c.is_ascii_digit())
+        .ok_or_else(|| anyhow::anyhow!("invalid cell reference {}", cell))?;
+    let (letters, digits) = cell.split_at(split);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_uppercase()) {
+        anyhow::bail!("invalid cell reference {}", cell);
+    }
+    let col = letters
+        .bytes()
+        .try_fold(0u32, |acc, b| {
+            acc.checked_mul(26)?.checked_add((b - b'A' + 1) as u32)
+        })
+        .ok_or_else(|| anyhow::anyhow!("column out of range in {}", cell))?;
+    let row: u32 = digits.parse()?;
+    if row == 0 {
+        anyhow::bail!("invalid cell reference {}", cell);
+    }
+    Ok((row - 1, col - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(sheet: Option<&str>, range: Option<&str>) -> Result<Vec<Vec<String>>> {
+        let reader = Box::new(SpreadsheetReader::new(
+            "fixtures/juventus.xlsx",
+            sheet.map(String::from),
+            range.map(String::from),
+        ));
+        let (headers, records) = reader.read()?;
+        let mut ret = vec![headers.iter().map(String::from).collect()];
+        for record in records {
+            ret.push(record?.iter().map(String::from).collect());
+        }
+        Ok(ret)
+    }
+
+    #[test]
+    fn test_parse_cell() -> Result<()> {
+        assert_eq!(parse_cell("A1")?, (0, 0));
+        assert_eq!(parse_cell("d20")?, (19, 3));
+        assert_eq!(parse_cell("AA3")?, (2, 26));
+        assert!(parse_cell("3A").is_err());
+        assert!(parse_cell("A0").is_err());
+        assert!(parse_cell("ZZZZZZZZZ1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_range() -> Result<()> {
+        assert_eq!(parse_range("B2:E40")?, ((1, 1), (39, 4)));
+        assert_eq!(parse_range("C3:C3")?, ((2, 2), (2, 2)));
+        assert!(parse_range("D20:A1").is_err());
+        assert!(parse_range("B1:A5").is_err());
+        assert!(parse_range("A5:B1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_spreadsheet_cells() -> Result<()> {
+        let rows = read(None, None)?;
+        assert_eq!(rows[0], vec!["Name", "DOB", "Kit Number", "Rating"]);
+        assert_eq!(rows[1], vec!["Wojciech Szczesny", "1990-04-18", "1", "7.5"]);
+        assert_eq!(rows.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_spreadsheet_sheet_and_range() -> Result<()> {
+        assert_eq!(
+            read(Some("Staff"), None)?[1],
+            vec!["Maurizio Sarri", "Coach"]
+        );
+        assert_eq!(read(Some("1"), None)?[0], vec!["Name", "Role"]);
+        assert!(read(Some("Coaches"), None).is_err());
+        let rows = read(None, Some("A1:B3"))?;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2], vec!["Mattia Perin", "1992-11-10"]);
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{process_genpass, TextSignFormat};
+use crate::{process_genpass, PasswordPolicy, TextSignFormat};
 use anyhow::Result;
 use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
 use rand::rngs::OsRng;
@@ -56,7 +56,10 @@ impl TextVerifier for Blake3 {
 }
 impl Blake3 {
     pub fn generate() -> Result<HashMap<&'static str, Vec<u8>>> {
-        let key = process_genpass(32, true, true, true, true)?;
+        let key = process_genpass(&PasswordPolicy {
+            length: 32,
+            ..Default::default()
+        })?;
         let mut map = HashMap::new();
         map.insert("auto_gen_blake3_key.txt", key.as_bytes().to_vec());
         Ok(map)
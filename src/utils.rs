@@ -1,5 +1,17 @@
 use anyhow::Result;
+use chrono::NaiveDate;
 use std::{fs::File, io::Read};
+
+const DATE_FORMATS: &[&str] = &[
+    "%Y-%m-%d",
+    "%Y/%m/%d",
+    "%d/%m/%Y",
+    "%m/%d/%Y",
+    "%b %d, %Y",
+    "%d %b %Y",
+    "%B %d, %Y",
+];
+
 pub fn get_reader(input: &str) -> Result<Box<dyn Read>> {
     let reader: Box<dyn Read> = if input == "-" {
         Box::new(std::io::stdin())
@@ -15,3 +27,20 @@ pub fn get_content(input: &str) -> Result<Vec<u8>> {
     reader.read_to_end(&mut buf)?;
     Ok(buf)
 }
+
+// Parse a date in one of the common formats, ignoring any trailing text like a time or "(29)"
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    DATE_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_and_remainder(value.trim(), fmt).ok())
+        .map(|(date, _)| date)
+}
+
+// Print whole numbers without a trailing ".0"
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        (value as i64).to_string()
+    } else {
+        value.to_string()
+    }
+}